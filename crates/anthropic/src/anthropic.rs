@@ -1,58 +1,74 @@
+pub mod batches;
+pub mod error;
 pub mod messages;
+pub mod model;
+pub mod models;
+pub mod pagination;
+pub mod response;
+pub mod retry;
+pub mod stream;
+pub mod structured;
+pub mod tools;
 
-use std::{str::FromStr, sync::Arc};
+#[cfg(test)]
+mod test_util;
+
+use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use http_client::{
-    http::{header::CONTENT_TYPE, Method, Request},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        request::Builder,
+        Method, Request, Response,
+    },
     AsyncBody, HttpClient, RequestBuilderExt,
 };
-use messages::{CreateMessageRequestWithStream, Requester};
+use messages::{
+    CountTokens, CountTokensRequest, CountTokensResponse, CreateMessageRequest,
+    CreateMessageRequestWithStream, Requester,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use retry::RetryPolicy;
 use secrecy::{ExposeSecret, SecretString};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
-const DEFAULT_API_ENDPOINT: &str = "https://api.anthropic.com";
-const DEFAULT_API_VERSION: &str = "2023-06-01";
+pub use crate::{
+    error::{ApiError, ApiErrorKind, Error, Result},
+    model::{Model, ModelCapabilities, Provider},
+    stream::{text_reader, text_stream, MessageStream, StreamEvent},
+    structured::MessagesStructured,
+    tools::{ToolRun, ToolRunner, TypedTool},
+};
 
-#[derive(Clone)]
-pub enum Model {
-    ClaudeThreeDotFiveSonnet,
-    ClaudeThreeSonnet,
-    ClaudeThreeOpus,
-    ClaudeThreeHaiku,
-}
+pub use schemars;
 
-impl ToString for Model {
-    fn to_string(&self) -> String {
-        match self {
-            Model::ClaudeThreeDotFiveSonnet => "claude-3-5-sonnet-latest".to_string(),
-            Model::ClaudeThreeSonnet => "claude-3-sonnet-20240229".to_string(),
-            Model::ClaudeThreeOpus => "claude-3-opus-20240229".to_string(),
-            Model::ClaudeThreeHaiku => "claude-3-haiku-20240307".to_string(),
-        }
-    }
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+
+    pub use crate::__model_type as model_type;
 }
 
-impl FromStr for Model {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "claude-3-5-sonnet-latest"
-            | "claude-3-5-sonnet-20241022"
-            | "claude-3-5-sonnet-20240620" => Ok(Model::ClaudeThreeDotFiveSonnet),
-            "claude-3-sonnet-20240229" => Ok(Model::ClaudeThreeSonnet),
-            "claude-3-opus-20240229" => Ok(Model::ClaudeThreeOpus),
-            "claude-3-haiku-20240307" => Ok(Model::ClaudeThreeHaiku),
-            _ => Err(anyhow::anyhow!("model not supported: {}", s)),
-        }
-    }
+const DEFAULT_API_ENDPOINT: &str = "https://api.anthropic.com";
+const DEFAULT_API_VERSION: &str = "2023-06-01";
+
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+pub(crate) fn path_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, PATH_SEGMENT).to_string()
 }
 
 pub struct Anthropic {
     api_key: SecretString,
     base_url: String,
     http_client: Arc<dyn HttpClient>,
+    retry_policy: RetryPolicy,
 }
 
 #[derive(Clone)]
@@ -60,6 +76,7 @@ pub struct AnthropicBuilder {
     api_key: Option<SecretString>,
     base_url: Option<String>,
     http_client: Option<Arc<dyn HttpClient>>,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl Anthropic {
@@ -68,8 +85,55 @@ impl Anthropic {
             api_key: None,
             base_url: None,
             http_client: None,
+            retry_policy: None,
         }
     }
+
+    fn request(&self, method: Method, url: String) -> Builder {
+        Request::builder()
+            .method(method)
+            .uri(url)
+            .header("x-api-key", self.api_key.expose_secret())
+            .header("anthropic-version", DEFAULT_API_VERSION)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: String,
+        body: Option<Value>,
+    ) -> Result<Response<AsyncBody>> {
+        self.send_url(method, format!("{}{}", self.base_url, path), body)
+            .await
+    }
+
+    async fn send_url(
+        &self,
+        method: Method,
+        url: String,
+        body: Option<Value>,
+    ) -> Result<Response<AsyncBody>> {
+        self.retry_policy
+            .send(self.http_client.as_ref(), || async {
+                let req = self.request(method.clone(), url.clone());
+
+                match &body {
+                    Some(body) => req
+                        .header(CONTENT_TYPE, "application/json")
+                        .json(body)
+                        .map_err(Error::validation),
+                    None => req.body(AsyncBody::default()).map_err(Error::validation),
+                }
+            })
+            .await
+    }
+
+    async fn send_json<T>(&self, method: Method, path: String, body: Option<Value>) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        response::read_json(self.send(method, path, body).await?).await
+    }
 }
 
 impl AnthropicBuilder {
@@ -94,22 +158,38 @@ impl AnthropicBuilder {
         self
     }
 
+    pub fn with_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    pub fn with_max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.retry_policy = Some(
+            self.retry_policy
+                .to_owned()
+                .unwrap_or_default()
+                .with_max_retries(max_retries),
+        );
+        self
+    }
+
     pub fn build(&self) -> Result<Anthropic> {
         Ok(Anthropic {
             api_key: self
                 .api_key
                 .to_owned()
                 .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok().map(|s| s.into()))
-                .ok_or_else(|| anyhow::anyhow!("API key is required"))?,
+                .ok_or_else(|| Error::auth("API key is required"))?,
             base_url: self
                 .base_url
                 .to_owned()
-                .or_else(|| std::env::var("ANTHROPIC_BASE_URL").ok().map(|s| s.into()))
+                .or_else(|| std::env::var("ANTHROPIC_BASE_URL").ok())
                 .unwrap_or_else(|| DEFAULT_API_ENDPOINT.into()),
             http_client: self
                 .http_client
                 .to_owned()
-                .ok_or_else(|| anyhow!("http client is required"))?,
+                .ok_or_else(|| Error::validation("http client is required"))?,
+            retry_policy: self.retry_policy.to_owned().unwrap_or_default(),
         })
     }
 }
@@ -128,22 +208,41 @@ impl Requester for Anthropic {
         "/v1/messages".into()
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     async fn request_builder(
         &self,
         url: String,
         body: CreateMessageRequestWithStream,
     ) -> Result<Request<AsyncBody>> {
-        let mut req = Request::builder().method(Method::POST).uri(url);
+        let mut req = self.request(Method::POST, url);
 
         if body.stream {
-            req = req.header("X-Stainless-Helper-Method", "stream");
+            req = req
+                .header(ACCEPT, "text/event-stream")
+                .header("X-Stainless-Helper-Method", "stream");
         }
 
-        Ok(req
-            .header("x-api-key", self.api_key.expose_secret())
-            .header("anthropic-version", DEFAULT_API_VERSION)
-            .header(CONTENT_TYPE, "application/json")
-            .json(body)?)
+        req.header(CONTENT_TYPE, "application/json")
+            .json(body)
+            .map_err(Error::validation)
+    }
+}
+
+#[async_trait]
+impl CountTokens for Anthropic {
+    async fn count_tokens(&self, request: CreateMessageRequest) -> Result<CountTokensResponse> {
+        self.send_json(
+            Method::POST,
+            "/v1/messages/count_tokens".into(),
+            Some(
+                serde_json::to_value(CountTokensRequest::from(request))
+                    .map_err(Error::validation)?,
+            ),
+        )
+        .await
     }
 }
 
@@ -157,6 +256,53 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_path_segment() {
+        assert_eq!(path_segment("msgbatch_01"), "msgbatch_01");
+        assert_eq!(
+            path_segment("claude-3-5-sonnet@20241022"),
+            "claude-3-5-sonnet%4020241022"
+        );
+        assert_eq!(path_segment("../a b/c?d#e"), "..%2Fa%20b%2Fc%3Fd%23e");
+    }
+
+    #[tokio::test]
+    async fn test_stream_request_accepts_event_stream() -> Result<()> {
+        let client = Anthropic::builder()
+            .with_api_key("sk-ant-test")
+            .with_http_client(Arc::new(HttpClientReqwest::default()))
+            .build()?;
+        let request = CreateMessageRequest::builder()
+            .model(Model::CLAUDE_3_HAIKU_20240307)
+            .messages(vec![Message::user("Hi!".into())])
+            .max_tokens(100)
+            .build()?;
+
+        let req = client
+            .request_builder(
+                "https://api.anthropic.com/v1/messages".into(),
+                CreateMessageRequestWithStream {
+                    create_message_request: request.clone(),
+                    stream: true,
+                },
+            )
+            .await?;
+        assert_eq!(req.headers()[ACCEPT], "text/event-stream");
+
+        let req = client
+            .request_builder(
+                "https://api.anthropic.com/v1/messages".into(),
+                CreateMessageRequestWithStream {
+                    create_message_request: request,
+                    stream: false,
+                },
+            )
+            .await?;
+        assert!(req.headers().get(ACCEPT).is_none());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_messages() -> Result<()> {
         let client = Anthropic::builder()
@@ -167,7 +313,7 @@ mod tests {
             client
                 .messages(
                     CreateMessageRequest::builder()
-                        .model(Model::ClaudeThreeHaiku)
+                        .model(Model::CLAUDE_3_HAIKU_20240307)
                         .messages(vec![Message::user("Hi!".into())])
                         .max_tokens(100)
                         .build()?,
@@ -187,7 +333,7 @@ mod tests {
         let mut s = client
             .messages_stream(
                 CreateMessageRequest::builder()
-                    .model(Model::ClaudeThreeHaiku)
+                    .model(Model::CLAUDE_3_HAIKU_20240307)
                     .messages(vec![Message::user("Hi!".into())])
                     .max_tokens(100)
                     .build()?,
@@ -0,0 +1,237 @@
+use std::pin::Pin;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{io::BufReader, AsyncBufReadExt, Stream, StreamExt};
+use http_client::http::Method;
+
+use crate::{
+    error::{Error, Result},
+    messages::{CreateMessageRequest, ErrorResponse, MessageResponse},
+    pagination::{Page, PageParams},
+    path_segment, response, Anthropic,
+};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub params: CreateMessageRequest,
+}
+
+impl BatchRequest {
+    pub fn new<S>(custom_id: S, params: CreateMessageRequest) -> Self
+    where
+        S: ToString,
+    {
+        Self {
+            custom_id: custom_id.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct CreateMessageBatchRequest {
+    pub requests: Vec<BatchRequest>,
+}
+
+impl From<Vec<BatchRequest>> for CreateMessageBatchRequest {
+    fn from(requests: Vec<BatchRequest>) -> Self {
+        Self { requests }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    InProgress,
+    Canceling,
+    Ended,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct RequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct MessageBatch {
+    pub id: String,
+    pub processing_status: ProcessingStatus,
+    pub request_counts: RequestCounts,
+    pub created_at: String,
+    pub expires_at: String,
+    pub ended_at: Option<String>,
+    pub cancel_initiated_at: Option<String>,
+    pub archived_at: Option<String>,
+    pub results_url: Option<String>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct DeletedMessageBatch {
+    pub id: String,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchResult {
+    Succeeded { message: MessageResponse },
+    Errored { error: ErrorResponse },
+    Canceled,
+    Expired,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BatchResultLine {
+    custom_id: String,
+    result: BatchResult,
+}
+
+#[async_trait]
+pub trait MessageBatches: Send + Sync {
+    async fn create_message_batch(
+        &self,
+        request: CreateMessageBatchRequest,
+    ) -> Result<MessageBatch>;
+
+    async fn retrieve_message_batch(&self, batch_id: &str) -> Result<MessageBatch>;
+
+    async fn list_message_batches(&self, params: PageParams) -> Result<Page<MessageBatch>>;
+
+    async fn cancel_message_batch(&self, batch_id: &str) -> Result<MessageBatch>;
+
+    async fn delete_message_batch(&self, batch_id: &str) -> Result<DeletedMessageBatch>;
+
+    async fn message_batch_results(
+        &self,
+        batch_id: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<(String, BatchResult)>> + Send>>>;
+}
+
+#[async_trait]
+impl MessageBatches for Anthropic {
+    async fn create_message_batch(
+        &self,
+        request: CreateMessageBatchRequest,
+    ) -> Result<MessageBatch> {
+        self.send_json(
+            Method::POST,
+            "/v1/messages/batches".into(),
+            Some(serde_json::to_value(request).map_err(Error::validation)?),
+        )
+        .await
+    }
+
+    async fn retrieve_message_batch(&self, batch_id: &str) -> Result<MessageBatch> {
+        self.send_json(
+            Method::GET,
+            format!("/v1/messages/batches/{}", path_segment(batch_id)),
+            None,
+        )
+        .await
+    }
+
+    async fn list_message_batches(&self, params: PageParams) -> Result<Page<MessageBatch>> {
+        self.send_json(
+            Method::GET,
+            format!("/v1/messages/batches{}", params.query()),
+            None,
+        )
+        .await
+    }
+
+    async fn cancel_message_batch(&self, batch_id: &str) -> Result<MessageBatch> {
+        self.send_json(
+            Method::POST,
+            format!("/v1/messages/batches/{}/cancel", path_segment(batch_id)),
+            None,
+        )
+        .await
+    }
+
+    async fn delete_message_batch(&self, batch_id: &str) -> Result<DeletedMessageBatch> {
+        self.send_json(
+            Method::DELETE,
+            format!("/v1/messages/batches/{}", path_segment(batch_id)),
+            None,
+        )
+        .await
+    }
+
+    async fn message_batch_results(
+        &self,
+        batch_id: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<(String, BatchResult)>> + Send>>> {
+        let results_url = self
+            .retrieve_message_batch(batch_id)
+            .await?
+            .results_url
+            .ok_or_else(|| {
+                Error::validation(format!("message batch {} has no results yet", batch_id))
+            })?;
+        let response = self.send_url(Method::GET, results_url, None).await?;
+        let response = response::error_for_status(response).await?;
+
+        let mut lines = BufReader::new(response.into_body()).lines();
+
+        Ok(stream! {
+            while let Some(line) = lines.next().await {
+                match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => {
+                        yield serde_json::from_str::<BatchResultLine>(&line)
+                            .map(|line| (line.custom_id, line.result))
+                            .map_err(Error::from);
+                    }
+                    Err(err) => {
+                        yield Err(Error::transport(err));
+                        break;
+                    }
+                }
+            }
+        }
+        .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ApiErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_batch_result_lines() -> Result<()> {
+        let succeeded = serde_json::from_str::<BatchResultLine>(
+            r#"{"custom_id":"a","result":{"type":"succeeded","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-haiku-20240307","content":[{"type":"text","text":"Hi"}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":3,"output_tokens":1}}}}"#,
+        )?;
+        assert_eq!(succeeded.custom_id, "a");
+        assert!(matches!(succeeded.result, BatchResult::Succeeded { .. }));
+
+        let errored = serde_json::from_str::<BatchResultLine>(
+            r#"{"custom_id":"b","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: Field required"}}}}"#,
+        )?;
+        match errored.result {
+            BatchResult::Errored { error } => {
+                assert_eq!(error.error.kind, ApiErrorKind::InvalidRequest)
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let canceled = serde_json::from_str::<BatchResultLine>(
+            r#"{"custom_id":"c","result":{"type":"canceled"}}"#,
+        )?;
+        assert!(matches!(canceled.result, BatchResult::Canceled));
+
+        let expired = serde_json::from_str::<BatchResultLine>(
+            r#"{"custom_id":"d","result":{"type":"expired"}}"#,
+        )?;
+        assert!(matches!(expired.result, BatchResult::Expired));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error("transport error: {0}")]
+    Transport(#[source] BoxError),
+    #[error("failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("unrecognized response type {}", .0["type"])]
+    UnknownResponse(serde_json::Value),
+    #[error("stream error: {0}")]
+    Stream(String),
+    #[error("failed to decode stream event {data}: {source}")]
+    EventDecode {
+        data: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("authentication error: {0}")]
+    Auth(#[source] BoxError),
+    #[error("invalid request: {0}")]
+    Validation(String),
+    #[error("unsupported by this provider: {0}")]
+    Unsupported(String),
+    #[error("invalid input for tool {name}: {source}")]
+    ToolInput {
+        name: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl Error {
+    pub fn transport<E>(err: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self::Transport(err.into())
+    }
+
+    pub fn auth<E>(err: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self::Auth(err.into())
+    }
+
+    pub fn validation<S>(message: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Validation(message.to_string())
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_) => true,
+            Self::Api(err) => match err.status {
+                Some(status) => crate::retry::is_retryable_status(status),
+                None => matches!(
+                    err.kind,
+                    ApiErrorKind::RateLimit | ApiErrorKind::Overloaded | ApiErrorKind::Api
+                ),
+            },
+            _ => false,
+        }
+    }
+
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Api(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub status: Option<u16>,
+    pub kind: ApiErrorKind,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(status) = self.status {
+            write!(f, " ({})", status)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " [request-id: {}]", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum ApiErrorKind {
+    InvalidRequest,
+    Authentication,
+    Permission,
+    NotFound,
+    RequestTooLarge,
+    RateLimit,
+    Api,
+    Overloaded,
+    Other(String),
+}
+
+impl ApiErrorKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 => ApiErrorKind::InvalidRequest,
+            401 => ApiErrorKind::Authentication,
+            403 => ApiErrorKind::Permission,
+            404 => ApiErrorKind::NotFound,
+            413 => ApiErrorKind::RequestTooLarge,
+            429 => ApiErrorKind::RateLimit,
+            529 => ApiErrorKind::Overloaded,
+            _ => ApiErrorKind::Api,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ApiErrorKind::InvalidRequest => "invalid_request_error",
+            ApiErrorKind::Authentication => "authentication_error",
+            ApiErrorKind::Permission => "permission_error",
+            ApiErrorKind::NotFound => "not_found_error",
+            ApiErrorKind::RequestTooLarge => "request_too_large",
+            ApiErrorKind::RateLimit => "rate_limit_error",
+            ApiErrorKind::Api => "api_error",
+            ApiErrorKind::Overloaded => "overloaded_error",
+            ApiErrorKind::Other(kind) => kind,
+        }
+    }
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for ApiErrorKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "invalid_request_error" => ApiErrorKind::InvalidRequest,
+            "authentication_error" => ApiErrorKind::Authentication,
+            "permission_error" => ApiErrorKind::Permission,
+            "not_found_error" => ApiErrorKind::NotFound,
+            "request_too_large" => ApiErrorKind::RequestTooLarge,
+            "rate_limit_error" => ApiErrorKind::RateLimit,
+            "api_error" => ApiErrorKind::Api,
+            "overloaded_error" => ApiErrorKind::Overloaded,
+            other => ApiErrorKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for ApiErrorKind {
+    fn from(kind: String) -> Self {
+        kind.as_str().into()
+    }
+}
+
+impl From<ApiErrorKind> for String {
+    fn from(kind: ApiErrorKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
@@ -1,14 +1,20 @@
 use std::{pin::Pin, sync::Arc};
 
-use anyhow::{anyhow, Result};
 use async_stream::stream;
 use async_trait::async_trait;
+use eventsource_stream::{EventStreamError, Eventsource};
 use futures::{Stream, StreamExt};
-use http_client::{http::request::Request, AsyncBody, HttpClient, ResponseAsyncBodyExt};
-use http_client_eventsource::{Event as SsrEvent, EventSource};
+use http_client::{http::request::Request, AsyncBody, HttpClient};
 
 use serde::Deserializer;
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{ApiError, ApiErrorKind, Error, Result},
+    model::Model,
+    response::{self, RawResponse},
+    retry::RetryPolicy,
+};
 
 pub trait AnthropicSdk: Messages + MessagesStream {}
 
@@ -44,6 +50,13 @@ impl Message {
             content,
         }
     }
+
+    pub fn assistant(content: Content) -> Self {
+        Self {
+            role: Role::Assistant,
+            content,
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Serialize)]
@@ -62,11 +75,17 @@ where
     }
 }
 
+impl Default for Content {
+    fn default() -> Self {
+        Self::Single(String::new())
+    }
+}
+
 fn content_deserializer<'de, D>(d: D) -> Result<Content, D::Error>
 where
     D: Deserializer<'de>,
 {
-    Ok(optional_content_deserializer::<_>(d)?.unwrap())
+    Ok(optional_content_deserializer::<_>(d)?.unwrap_or_default())
 }
 
 fn optional_content_deserializer<'de, D>(d: D) -> Result<Option<Content>, D::Error>
@@ -77,9 +96,9 @@ where
 
     #[derive(Deserialize)]
     #[serde(untagged)]
-    enum ContentDeserializeHelper<'a> {
+    enum ContentDeserializeHelper {
         None,
-        Single(&'a str),
+        Single(String),
         Multi(Vec<ContentPart>),
     }
 
@@ -87,7 +106,7 @@ where
         Ok(ContentDeserializeHelper::None) => Ok(None),
         Ok(ContentDeserializeHelper::Multi(r)) => Ok(Some(Content::Multi(r))),
         Ok(ContentDeserializeHelper::Single(s)) if s.is_empty() => Ok(None),
-        Ok(ContentDeserializeHelper::Single(s)) => Ok(Some(Content::Single(s.to_string()))),
+        Ok(ContentDeserializeHelper::Single(s)) => Ok(Some(Content::Single(s))),
         Err(err) => Err(err),
     }
 }
@@ -105,38 +124,450 @@ pub enum MediaType {
     ImageWebp,
 }
 
+impl MediaType {
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime.split(';').next()?.trim() {
+            "image/jpeg" | "image/jpg" => Some(MediaType::ImageJpeg),
+            "image/png" => Some(MediaType::ImagePng),
+            "image/gif" => Some(MediaType::ImageGif),
+            "image/webp" => Some(MediaType::ImageWebp),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct ImageSource {
-    #[serde(rename = "type")]
-    pub kind: String,
-    pub media_type: MediaType,
-    pub data: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImageSource {
+    Base64 { media_type: MediaType, data: String },
+    Url { url: String },
+    File { file_id: String },
+}
+
+impl ImageSource {
+    pub fn base64<S>(media_type: MediaType, data: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Base64 {
+            media_type,
+            data: data.to_string(),
+        }
+    }
+
+    pub fn url<S>(url: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Url {
+            url: url.to_string(),
+        }
+    }
+
+    pub fn file<S>(file_id: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::File {
+            file_id: file_id.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub enum DocumentMediaType {
+    #[serde(rename = "application/pdf")]
+    ApplicationPdf,
+    #[serde(rename = "text/plain")]
+    TextPlain,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DocumentSource {
+    Base64 {
+        media_type: DocumentMediaType,
+        data: String,
+    },
+    Text {
+        media_type: DocumentMediaType,
+        data: String,
+    },
+    Content {
+        #[serde(deserialize_with = "content_deserializer")]
+        content: Content,
+    },
+}
+
+impl DocumentSource {
+    pub fn pdf<S>(data: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Base64 {
+            media_type: DocumentMediaType::ApplicationPdf,
+            data: data.to_string(),
+        }
+    }
+
+    pub fn text<S>(data: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Text {
+            media_type: DocumentMediaType::TextPlain,
+            data: data.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct CitationsConfig {
+    pub enabled: bool,
+}
+
+impl CitationsConfig {
+    pub fn enabled() -> Self {
+        Self { enabled: true }
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
+pub enum Citation {
+    CharLocation {
+        cited_text: String,
+        document_index: u32,
+        document_title: Option<String>,
+        start_char_index: u32,
+        end_char_index: u32,
+    },
+    PageLocation {
+        cited_text: String,
+        document_index: u32,
+        document_title: Option<String>,
+        start_page_number: u32,
+        end_page_number: u32,
+    },
+    ContentBlockLocation {
+        cited_text: String,
+        document_index: u32,
+        document_title: Option<String>,
+        start_block_index: u32,
+        end_block_index: u32,
+    },
+    SearchResultLocation {
+        cited_text: String,
+        source: String,
+        title: Option<String>,
+        search_result_index: u32,
+        start_block_index: u32,
+        end_block_index: u32,
+    },
+    WebSearchResultLocation {
+        cited_text: String,
+        url: String,
+        title: Option<String>,
+        encrypted_index: String,
+    },
+    #[serde(untagged, skip_deserializing)]
+    Unknown(Value),
+}
+
+const CITATION_TYPES: &[&str] = &[
+    "char_location",
+    "page_location",
+    "content_block_location",
+    "search_result_location",
+    "web_search_result_location",
+];
+
+impl<'de> serde::Deserialize<'de> for Citation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_known_type(
+            deserializer,
+            CITATION_TYPES,
+            Citation::deserialize,
+            Citation::Unknown,
+        )
+    }
+}
+
+impl serde::Serialize for Citation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Citation::serialize(self, serializer)
+    }
+}
+
+impl Citation {
+    pub fn cited_text(&self) -> &str {
+        match self {
+            Citation::CharLocation { cited_text, .. }
+            | Citation::PageLocation { cited_text, .. }
+            | Citation::ContentBlockLocation { cited_text, .. }
+            | Citation::SearchResultLocation { cited_text, .. }
+            | Citation::WebSearchResultLocation { cited_text, .. } => cited_text,
+            Citation::Unknown(value) => value
+                .get("cited_text")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheControl {
+    Ephemeral {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ttl: Option<CacheTtl>,
+    },
+}
+
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self::Ephemeral { ttl: None }
+    }
+
+    pub fn ephemeral_with_ttl(ttl: CacheTtl) -> Self {
+        Self::Ephemeral { ttl: Some(ttl) }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
 pub enum ContentPart {
     Text {
         text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        citations: Option<Vec<Citation>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     TextDelta {
         text: String,
     },
     Image {
         source: ImageSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    Document {
+        source: DocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        citations: Option<CitationsConfig>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    SearchResult {
+        source: String,
+        title: String,
+        content: Vec<ContentPart>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        citations: Option<CitationsConfig>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     ToolResult {
         tool_use_id: String,
-        content: String,
+        #[serde(default, deserialize_with = "content_deserializer")]
+        content: Content,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     ToolUse {
         id: String,
         name: String,
         input: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
     },
     InputJsonDelta {
         partial_json: String,
     },
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    CitationsDelta {
+        citation: Citation,
+    },
+    #[serde(untagged, skip_deserializing)]
+    Unknown(Value),
+}
+
+const CONTENT_PART_TYPES: &[&str] = &[
+    "text",
+    "text_delta",
+    "image",
+    "document",
+    "search_result",
+    "tool_result",
+    "tool_use",
+    "input_json_delta",
+    "thinking",
+    "redacted_thinking",
+    "thinking_delta",
+    "signature_delta",
+    "citations_delta",
+];
+
+impl<'de> serde::Deserialize<'de> for ContentPart {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_known_type(
+            deserializer,
+            CONTENT_PART_TYPES,
+            ContentPart::deserialize,
+            ContentPart::Unknown,
+        )
+    }
+}
+
+impl serde::Serialize for ContentPart {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        ContentPart::serialize(self, serializer)
+    }
+}
+
+/// Deserializes a `type`-tagged value, falling back to `unknown` only when the
+/// tag isn't one of `known`, so malformed known variants still fail.
+fn deserialize_known_type<'de, D, T>(
+    deserializer: D,
+    known: &[&str],
+    deserialize: fn(Value) -> serde_json::Result<T>,
+    unknown: fn(Value) -> T,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::{de::Error as _, Deserialize};
+
+    let value = Value::deserialize(deserializer)?;
+    match value.get("type").and_then(Value::as_str) {
+        Some(kind) if known.contains(&kind) => deserialize(value).map_err(D::Error::custom),
+        _ => Ok(unknown(value)),
+    }
+}
+
+impl ContentPart {
+    pub fn tool_result<S>(tool_use_id: S, content: Content) -> Self
+    where
+        S: ToString,
+    {
+        Self::ToolResult {
+            tool_use_id: tool_use_id.to_string(),
+            content,
+            is_error: None,
+            cache_control: None,
+        }
+    }
+
+    pub fn tool_error<S>(tool_use_id: S, content: Content) -> Self
+    where
+        S: ToString,
+    {
+        Self::ToolResult {
+            tool_use_id: tool_use_id.to_string(),
+            content,
+            is_error: Some(true),
+            cache_control: None,
+        }
+    }
+
+    pub fn document(source: DocumentSource) -> Self {
+        Self::Document {
+            source,
+            title: None,
+            context: None,
+            citations: None,
+            cache_control: None,
+        }
+    }
+
+    pub fn citations(&self) -> &[Citation] {
+        match self {
+            ContentPart::Text {
+                citations: Some(citations),
+                ..
+            } => citations,
+            _ => &[],
+        }
+    }
+
+    pub fn cache_control(&self) -> Option<&CacheControl> {
+        match self {
+            ContentPart::Text { cache_control, .. }
+            | ContentPart::Image { cache_control, .. }
+            | ContentPart::Document { cache_control, .. }
+            | ContentPart::SearchResult { cache_control, .. }
+            | ContentPart::ToolResult { cache_control, .. }
+            | ContentPart::ToolUse { cache_control, .. } => cache_control.as_ref(),
+            ContentPart::TextDelta { .. }
+            | ContentPart::InputJsonDelta { .. }
+            | ContentPart::Thinking { .. }
+            | ContentPart::RedactedThinking { .. }
+            | ContentPart::ThinkingDelta { .. }
+            | ContentPart::SignatureDelta { .. }
+            | ContentPart::CitationsDelta { .. }
+            | ContentPart::Unknown(_) => None,
+        }
+    }
+
+    pub fn with_cache_control(mut self, value: CacheControl) -> Self {
+        match &mut self {
+            ContentPart::Text { cache_control, .. }
+            | ContentPart::Image { cache_control, .. }
+            | ContentPart::Document { cache_control, .. }
+            | ContentPart::SearchResult { cache_control, .. }
+            | ContentPart::ToolResult { cache_control, .. }
+            | ContentPart::ToolUse { cache_control, .. } => *cache_control = Some(value),
+            ContentPart::TextDelta { .. }
+            | ContentPart::InputJsonDelta { .. }
+            | ContentPart::Thinking { .. }
+            | ContentPart::RedactedThinking { .. }
+            | ContentPart::ThinkingDelta { .. }
+            | ContentPart::SignatureDelta { .. }
+            | ContentPart::CitationsDelta { .. }
+            | ContentPart::Unknown(_) => {}
+        }
+        self
+    }
 }
 
 impl<S> From<S> for ContentPart
@@ -146,6 +577,8 @@ where
     fn from(text: S) -> Self {
         Self::Text {
             text: text.as_ref().to_string(),
+            citations: None,
+            cache_control: None,
         }
     }
 }
@@ -158,7 +591,7 @@ pub struct Metadata {
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum ToolChoiceKind {
     Auto,
     Any,
@@ -167,7 +600,7 @@ pub enum ToolChoiceKind {
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct ToolChoice {
-    #[serde(rename = "type")]
+    #[serde(flatten)]
     pub kind: ToolChoiceKind,
 }
 
@@ -177,12 +610,56 @@ impl From<ToolChoiceKind> for ToolChoice {
     }
 }
 
-#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct ToolInputSchema {
-    #[serde(rename = "type")]
-    pub kind: String,
-    pub properties: Value,
-    pub required: Vec<String>,
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "Map<String, Value>", into = "Map<String, Value>")]
+pub struct ToolInputSchema(Map<String, Value>);
+
+impl ToolInputSchema {
+    pub fn object(properties: Value, required: Vec<String>) -> Self {
+        let mut schema = Map::new();
+        schema.insert("type".into(), "object".into());
+        schema.insert("properties".into(), properties);
+        schema.insert("required".into(), required.into());
+        Self(schema)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn as_map(&self) -> &Map<String, Value> {
+        &self.0
+    }
+}
+
+impl TryFrom<Map<String, Value>> for ToolInputSchema {
+    type Error = Error;
+
+    fn try_from(schema: Map<String, Value>) -> Result<Self> {
+        match schema.get("type").and_then(Value::as_str) {
+            Some("object") => Ok(Self(schema)),
+            _ => Err(Error::validation(
+                "tool input_schema must be a JSON Schema with type \"object\"",
+            )),
+        }
+    }
+}
+
+impl TryFrom<Value> for ToolInputSchema {
+    type Error = Error;
+
+    fn try_from(schema: Value) -> Result<Self> {
+        match schema {
+            Value::Object(schema) => schema.try_into(),
+            _ => Err(Error::validation("tool input_schema must be a JSON object")),
+        }
+    }
+}
+
+impl From<ToolInputSchema> for Map<String, Value> {
+    fn from(schema: ToolInputSchema) -> Self {
+        schema.0
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -192,6 +669,28 @@ pub struct Tool {
     pub name: String,
     #[serde(rename = "input_schema")]
     pub input_schema: ToolInputSchema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingConfig {
+    Enabled { budget_tokens: u32 },
+    Disabled,
+}
+
+impl ThinkingConfig {
+    pub fn enabled(budget_tokens: u32) -> Self {
+        Self::Enabled { budget_tokens }
+    }
+
+    pub fn budget_tokens(&self) -> Option<u32> {
+        match self {
+            ThinkingConfig::Enabled { budget_tokens } => Some(*budget_tokens),
+            ThinkingConfig::Disabled => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -213,6 +712,8 @@ pub struct CreateMessageRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "tool_choice")]
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -225,6 +726,15 @@ pub struct CreateMessageRequest {
     pub top_p: Option<f32>,
 }
 
+impl CreateMessageRequest {
+    pub fn validate(&self) -> Result<()> {
+        match Model::from(self.model.as_str()).capabilities() {
+            Some(capabilities) => capabilities.validate(self),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct CreateMessageRequestWithStream {
     #[serde(flatten)]
@@ -232,6 +742,41 @@ pub struct CreateMessageRequestWithStream {
     pub stream: bool,
 }
 
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct CountTokensRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "optional_content_deserializer"
+    )]
+    pub system: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+}
+
+impl From<CreateMessageRequest> for CountTokensRequest {
+    fn from(value: CreateMessageRequest) -> Self {
+        Self {
+            model: value.model,
+            messages: value.messages,
+            system: value.system,
+            thinking: value.thinking,
+            tool_choice: value.tool_choice,
+            tools: value.tools,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct CountTokensResponse {
+    pub input_tokens: u32,
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct IncomingCreateMessageRequest {
     #[serde(flatten)]
@@ -243,6 +788,8 @@ pub struct IncomingCreateMessageRequest {
 #[serde(rename_all = "snake_case")]
 pub enum MessageResponseKind {
     Message,
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -256,14 +803,40 @@ pub enum StopReason {
     StopSequence,
     #[serde(rename = "tool_use")]
     ToolUse,
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct Usage {
     #[serde(rename = "input_tokens")]
     pub input_tokens: Option<u32>,
     #[serde(rename = "output_tokens")]
     pub output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        fn add(total: Option<u32>, value: Option<u32>) -> Option<u32> {
+            match (total, value) {
+                (None, None) => None,
+                (total, value) => Some(total.unwrap_or_default() + value.unwrap_or_default()),
+            }
+        }
+
+        self.input_tokens = add(self.input_tokens, other.input_tokens);
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens = add(
+            self.cache_creation_input_tokens,
+            other.cache_creation_input_tokens,
+        );
+        self.cache_read_input_tokens =
+            add(self.cache_read_input_tokens, other.cache_read_input_tokens);
+    }
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -286,31 +859,78 @@ pub struct MessageResponseStream {
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
 pub enum CreateMessageResponse {
     Message(MessageResponse),
-    Error { error: ErrorDetails },
+    Error {
+        error: ErrorDetails,
+    },
+    #[serde(untagged, skip_deserializing)]
+    Unknown(Value),
+}
+
+const CREATE_MESSAGE_RESPONSE_TYPES: &[&str] = &["message", "error"];
+
+impl<'de> serde::Deserialize<'de> for CreateMessageResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_known_type(
+            deserializer,
+            CREATE_MESSAGE_RESPONSE_TYPES,
+            CreateMessageResponse::deserialize,
+            CreateMessageResponse::Unknown,
+        )
+    }
+}
+
+impl serde::Serialize for CreateMessageResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        CreateMessageResponse::serialize(self, serializer)
+    }
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct ErrorDetails {
     #[serde(rename = "type")]
-    pub kind: String,
+    pub kind: ApiErrorKind,
     pub message: String,
 }
 
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorDetails,
+}
+
+impl From<ErrorDetails> for ApiError {
+    fn from(error: ErrorDetails) -> Self {
+        ApiError {
+            status: None,
+            kind: error.kind,
+            message: error.message,
+            request_id: None,
+        }
+    }
+}
+
 pub struct CreateMessageRequestBuilder {
     model: Option<String>,
     messages: Option<Vec<Message>>,
     max_tokens: Option<u32>,
     metadata: Option<Metadata>,
     stop_sequences: Option<Vec<String>>,
-    system: Option<String>,
+    system: Option<Content>,
     temperature: Option<f32>,
+    thinking: Option<ThinkingConfig>,
     tool_choice: Option<ToolChoice>,
     tools: Option<Vec<Tool>>,
     top_k: Option<u32>,
     top_p: Option<f32>,
+    validate_capabilities: bool,
 }
 
 impl CreateMessageRequest {
@@ -323,10 +943,12 @@ impl CreateMessageRequest {
             stop_sequences: None,
             system: None,
             temperature: None,
+            thinking: None,
             tool_choice: None,
             tools: None,
             top_k: None,
             top_p: None,
+            validate_capabilities: false,
         }
     }
 }
@@ -360,8 +982,11 @@ impl CreateMessageRequestBuilder {
         self
     }
 
-    pub fn system(mut self, system: String) -> Self {
-        self.system = Some(system);
+    pub fn system<C>(mut self, system: C) -> Self
+    where
+        C: Into<Content>,
+    {
+        self.system = Some(system.into());
         self
     }
 
@@ -370,6 +995,11 @@ impl CreateMessageRequestBuilder {
         self
     }
 
+    pub fn thinking(mut self, thinking: ThinkingConfig) -> Self {
+        self.thinking = Some(thinking);
+        self
+    }
+
     pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
         self.tool_choice = Some(tool_choice);
         self
@@ -390,24 +1020,39 @@ impl CreateMessageRequestBuilder {
         self
     }
 
+    /// Check the request against the known capabilities of its model on
+    /// `build`. Models missing from the capability table are never rejected.
+    pub fn validate_capabilities(mut self) -> Self {
+        self.validate_capabilities = true;
+        self
+    }
+
     pub fn build(self) -> Result<CreateMessageRequest> {
-        Ok(CreateMessageRequest {
-            model: self.model.ok_or_else(|| anyhow!("model is required"))?,
+        let request = CreateMessageRequest {
+            model: self
+                .model
+                .ok_or_else(|| Error::validation("model is required"))?,
             messages: self
                 .messages
-                .ok_or_else(|| anyhow!("messages is required"))?,
+                .ok_or_else(|| Error::validation("messages is required"))?,
             max_tokens: self
                 .max_tokens
-                .ok_or_else(|| anyhow!("max_tokens is required"))?,
+                .ok_or_else(|| Error::validation("max_tokens is required"))?,
             metadata: self.metadata,
             stop_sequences: self.stop_sequences,
-            system: self.system.map(|s| s.into()),
+            system: self.system,
             temperature: self.temperature,
+            thinking: self.thinking,
             tool_choice: self.tool_choice,
             tools: self.tools,
             top_k: self.top_k,
             top_p: self.top_p,
-        })
+        };
+        if self.validate_capabilities {
+            request.validate()?;
+        }
+
+        Ok(request)
     }
 }
 
@@ -419,6 +1064,10 @@ pub trait Requester: Send + Sync {
 
     fn endpoint_url(&self, body: &CreateMessageRequestWithStream) -> String;
 
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
     async fn request_builder(
         &self,
         url: String,
@@ -428,7 +1077,12 @@ pub trait Requester: Send + Sync {
 
 #[async_trait]
 pub trait Messages: Send + Sync {
-    async fn messages(&self, request: CreateMessageRequest) -> Result<CreateMessageResponse>;
+    async fn messages(&self, request: CreateMessageRequest) -> Result<MessageResponse>;
+}
+
+#[async_trait]
+pub trait CountTokens: Send + Sync {
+    async fn count_tokens(&self, request: CreateMessageRequest) -> Result<CountTokensResponse>;
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -438,7 +1092,7 @@ pub struct EventMessageDelta {
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
+#[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
 pub enum Event {
     Ping,
     MessageStart {
@@ -460,7 +1114,45 @@ pub enum Event {
         usage: Usage,
     },
     MessageStop,
-    Error(ErrorDetails),
+    Error {
+        error: ErrorDetails,
+    },
+    #[serde(untagged, skip_deserializing)]
+    Unknown(Value),
+}
+
+const EVENT_TYPES: &[&str] = &[
+    "ping",
+    "message_start",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "message_delta",
+    "message_stop",
+    "error",
+];
+
+impl<'de> serde::Deserialize<'de> for Event {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_known_type(
+            deserializer,
+            EVENT_TYPES,
+            Event::deserialize,
+            Event::Unknown,
+        )
+    }
+}
+
+impl serde::Serialize for Event {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Event::serialize(self, serializer)
+    }
 }
 
 #[async_trait]
@@ -471,37 +1163,26 @@ pub trait MessagesStream {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>>;
 }
 
+#[async_trait]
+pub trait MessagesWithRawResponse {
+    async fn messages_with_raw_response(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<RawResponse<MessageResponse>>;
+
+    async fn messages_stream_with_raw_response(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<RawResponse<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>>>;
+}
+
 #[async_trait]
 impl<T> Messages for T
 where
     T: Requester,
 {
-    async fn messages(&self, request: CreateMessageRequest) -> Result<CreateMessageResponse> {
-        let create_message_request_with_stream = CreateMessageRequestWithStream {
-            create_message_request: request,
-            stream: false,
-        };
-
-        let request = self
-            .request_builder(
-                format!(
-                    "{}{}",
-                    self.base_url(),
-                    self.endpoint_url(&create_message_request_with_stream)
-                ),
-                create_message_request_with_stream,
-            )
-            .await?;
-
-        let text = self
-            .http_client()
-            .send(request)
-            .await
-            .map_err(|e| anyhow!(e))?
-            .text()
-            .await?;
-        dbg!(&text);
-        Ok(serde_json::from_str(&text)?)
+    async fn messages(&self, request: CreateMessageRequest) -> Result<MessageResponse> {
+        Ok(self.messages_with_raw_response(request).await?.into_data())
     }
 }
 
@@ -514,42 +1195,576 @@ where
         &self,
         request: CreateMessageRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>> {
+        Ok(self
+            .messages_stream_with_raw_response(request)
+            .await?
+            .into_data())
+    }
+}
+
+#[async_trait]
+impl<T> MessagesWithRawResponse for T
+where
+    T: Requester,
+{
+    async fn messages_with_raw_response(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<RawResponse<MessageResponse>> {
         let create_message_request_with_stream = CreateMessageRequestWithStream {
             create_message_request: request,
-            stream: true,
+            stream: false,
         };
 
-        let request = self
-            .request_builder(
-                format!(
-                    "{}{}",
-                    self.base_url(),
-                    self.endpoint_url(&create_message_request_with_stream)
-                ),
-                create_message_request_with_stream,
-            )
+        let url = format!(
+            "{}{}",
+            self.base_url(),
+            self.endpoint_url(&create_message_request_with_stream)
+        );
+
+        let response = self
+            .retry_policy()
+            .send(self.http_client().as_ref(), || {
+                self.request_builder(url.clone(), create_message_request_with_stream.clone())
+            })
             .await?;
 
-        let http_client = self.http_client();
-
-        Ok(stream! {
-            let mut es = http_client.event_source(request)?;
-            while let Some(event) = es.next().await {
-                match event {
-                    Ok(SsrEvent::Open) => continue,
-                    Ok(SsrEvent::Message(message_event)) => {
-                        yield Ok(serde_json::from_str::<Event>(&message_event.data,)?)
-                    },
-                    Err(err) => {
-                        es.close();
-                        match err {
-                            http_client_eventsource::error::Error::StreamEnded => continue,
-                            _ => yield Err(anyhow!("unexpected error")),
+        let status = response.status();
+        let headers = response.headers().clone();
+
+        match response::read_json(response).await? {
+            CreateMessageResponse::Message(message) => {
+                Ok(RawResponse::from_parts(status, headers, message))
+            }
+            CreateMessageResponse::Error { error } => Err(ApiError {
+                status: Some(status.as_u16()),
+                kind: error.kind,
+                message: error.message,
+                request_id: response::request_id(&headers),
+            }
+            .into()),
+            CreateMessageResponse::Unknown(value) => Err(Error::UnknownResponse(value)),
+        }
+    }
+
+    async fn messages_stream_with_raw_response(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<RawResponse<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>>> {
+        let create_message_request_with_stream = CreateMessageRequestWithStream {
+            create_message_request: request,
+            stream: true,
+        };
+
+        let url = format!(
+            "{}{}",
+            self.base_url(),
+            self.endpoint_url(&create_message_request_with_stream)
+        );
+
+        let mut backoff = self.retry_policy().backoff();
+        loop {
+            let response = backoff
+                .send(self.http_client().as_ref(), || {
+                    self.request_builder(url.clone(), create_message_request_with_stream.clone())
+                })
+                .await?;
+            let response = response::error_for_status(response).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let mut events = event_stream(
+                response::body_stream(response.into_body()),
+                response::request_id(&headers),
+            );
+
+            // Once an event has been handed out the stream can't be replayed, so the
+            // first one is awaited here where a failed attempt can still be retried.
+            match events.next().await {
+                Some(Err(err)) if err.is_retryable() && backoff.wait(None).await => continue,
+                first => {
+                    return Ok(RawResponse::from_parts(
+                        status,
+                        headers,
+                        futures::stream::iter(first).chain(events).boxed(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn event_stream<S>(
+    body: S,
+    request_id: Option<String>,
+) -> Pin<Box<dyn Stream<Item = Result<Event>> + Send>>
+where
+    S: Stream<Item = std::io::Result<Vec<u8>>> + Send + 'static,
+{
+    let mut es = body.eventsource().boxed();
+
+    stream! {
+        let mut message_stopped = false;
+
+        while let Some(event) = es.next().await {
+            match event {
+                Ok(message_event) => match serde_json::from_str::<Event>(&message_event.data) {
+                    Ok(Event::Error { error }) => {
+                        yield Err(ApiError {
+                            request_id: request_id.clone(),
+                            ..error.into()
                         }
+                        .into());
+                        return;
+                    }
+                    Ok(event) => {
+                        message_stopped |= matches!(event, Event::MessageStop);
+                        yield Ok(event);
                     }
+                    Err(source) => {
+                        yield Err(Error::EventDecode {
+                            data: message_event.data,
+                            source,
+                        });
+                        return;
+                    }
+                },
+                Err(EventStreamError::Transport(err)) => {
+                    yield Err(Error::transport(err));
+                    return;
+                }
+                Err(err) => {
+                    yield Err(Error::Stream(err.to_string()));
+                    return;
                 }
             }
         }
-        .boxed())
+
+        if !message_stopped {
+            yield Err(Error::Stream("stream ended without message_stop".into()));
+        }
+    }
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens_request_drops_sampling_fields() -> Result<()> {
+        let request = CreateMessageRequest::builder()
+            .model("claude-3-haiku-20240307")
+            .messages(vec![Message::user("Hi!".into())])
+            .system("Be brief.")
+            .max_tokens(100)
+            .temperature(0.5)
+            .build()?;
+
+        assert_eq!(
+            serde_json::to_value(CountTokensRequest::from(request))?,
+            serde_json::json!({
+                "model": "claude-3-haiku-20240307",
+                "messages": [{ "role": "user", "content": "Hi!" }],
+                "system": "Be brief.",
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_control_serialization() -> Result<()> {
+        let system = Content::Multi(vec![ContentPart::from("A long system prompt.")
+            .with_cache_control(CacheControl::ephemeral_with_ttl(CacheTtl::OneHour))]);
+
+        assert_eq!(
+            serde_json::to_value(&system)?,
+            serde_json::json!([{
+                "type": "text",
+                "text": "A long system prompt.",
+                "cache_control": { "type": "ephemeral", "ttl": "1h" },
+            }])
+        );
+
+        let usage = serde_json::from_str::<Usage>(
+            r#"{"input_tokens":10,"output_tokens":5,"cache_creation_input_tokens":2048,"cache_read_input_tokens":0}"#,
+        )?;
+        assert_eq!(usage.cache_creation_input_tokens, Some(2048));
+        assert_eq!(usage.cache_read_input_tokens, Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_thinking_blocks_round_trip() -> Result<()> {
+        let response = serde_json::from_str::<MessageResponse>(
+            r#"{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-7-sonnet-20250219","content":[{"type":"thinking","thinking":"Let me think.","signature":"sig"},{"type":"redacted_thinking","data":"opaque"},{"type":"text","text":"Done."}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":3,"output_tokens":1}}"#,
+        )?;
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content::Multi(response.content),
+        };
+        let value = serde_json::to_value(&message)?;
+        assert_eq!(
+            value["content"][0],
+            serde_json::json!({ "type": "thinking", "thinking": "Let me think.", "signature": "sig" })
+        );
+        assert_eq!(
+            value["content"][1],
+            serde_json::json!({ "type": "redacted_thinking", "data": "opaque" })
+        );
+
+        let delta = serde_json::from_str::<Event>(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+        )?;
+        assert!(matches!(
+            delta,
+            Event::ContentBlockDelta {
+                delta: ContentPart::SignatureDelta { .. },
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_serialization() -> Result<()> {
+        let document = ContentPart::Document {
+            source: DocumentSource::text("The grass is green."),
+            title: Some("Facts".into()),
+            context: None,
+            citations: Some(CitationsConfig::enabled()),
+            cache_control: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&document)?,
+            serde_json::json!({
+                "type": "document",
+                "source": { "type": "text", "media_type": "text/plain", "data": "The grass is green." },
+                "title": "Facts",
+                "citations": { "enabled": true },
+            })
+        );
+
+        let custom = serde_json::from_value::<ContentPart>(serde_json::json!({
+            "type": "document",
+            "source": { "type": "content", "content": [{ "type": "text", "text": "First chunk" }] },
+        }))?;
+        assert!(matches!(
+            custom,
+            ContentPart::Document {
+                source: DocumentSource::Content {
+                    content: Content::Multi(_)
+                },
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_citations_deserialization() -> Result<()> {
+        let text = serde_json::from_value::<ContentPart>(serde_json::json!({
+            "type": "text",
+            "text": "the grass is green",
+            "citations": [{
+                "type": "char_location",
+                "cited_text": "The grass is green.",
+                "document_index": 0,
+                "document_title": "Facts",
+                "start_char_index": 0,
+                "end_char_index": 20,
+            }],
+        }))?;
+        assert_eq!(text.citations().len(), 1);
+        assert_eq!(text.citations()[0].cited_text(), "The grass is green.");
+
+        let delta = serde_json::from_str::<Event>(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"citations_delta","citation":{"type":"page_location","cited_text":"Revenue grew.","document_index":0,"document_title":null,"start_page_number":3,"end_page_number":4}}}"#,
+        )?;
+        assert!(matches!(
+            delta,
+            Event::ContentBlockDelta {
+                delta: ContentPart::CitationsDelta {
+                    citation: Citation::PageLocation { .. }
+                },
+                ..
+            }
+        ));
+
+        let delta = serde_json::from_str::<Event>(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"citations_delta","citation":{"type":"video_location","cited_text":"A clip.","start_time":3}}}"#,
+        )?;
+        match delta {
+            Event::ContentBlockDelta {
+                delta: ContentPart::CitationsDelta { citation },
+                ..
+            } => {
+                assert!(matches!(&citation, Citation::Unknown(value) if value["start_time"] == 3));
+                assert_eq!(citation.cited_text(), "A clip.");
+                assert_eq!(serde_json::to_value(&citation)?["type"], "video_location");
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert!(serde_json::from_value::<Citation>(serde_json::json!({
+            "type": "char_location",
+            "cited_text": "The grass is green."
+        }))
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_sources() -> Result<()> {
+        assert_eq!(
+            serde_json::to_value(ImageSource::url("https://example.com/cat.png"))?,
+            serde_json::json!({ "type": "url", "url": "https://example.com/cat.png" })
+        );
+        assert_eq!(
+            serde_json::to_value(ImageSource::file("file_01"))?,
+            serde_json::json!({ "type": "file", "file_id": "file_01" })
+        );
+        assert_eq!(
+            serde_json::to_value(ImageSource::base64(MediaType::ImagePng, "aGk="))?,
+            serde_json::json!({ "type": "base64", "media_type": "image/png", "data": "aGk=" })
+        );
+
+        assert!(matches!(
+            MediaType::from_mime("image/jpeg; charset=binary"),
+            Some(MediaType::ImageJpeg)
+        ));
+        assert!(MediaType::from_mime("text/html").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_result_content() -> Result<()> {
+        let result = ContentPart::tool_error("toolu_01", "city not found".into());
+        assert_eq!(
+            serde_json::to_value(&result)?,
+            serde_json::json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_01",
+                "content": "city not found",
+                "is_error": true
+            })
+        );
+
+        let result = serde_json::from_value::<ContentPart>(serde_json::json!({
+            "type": "tool_result",
+            "tool_use_id": "toolu_02",
+            "content": [
+                { "type": "text", "text": "Here is the chart" },
+                { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "aGk=" } }
+            ]
+        }))?;
+        match result {
+            ContentPart::ToolResult {
+                content: Content::Multi(parts),
+                is_error: None,
+                ..
+            } => {
+                assert!(matches!(parts[0], ContentPart::Text { .. }));
+                assert!(matches!(parts[1], ContentPart::Image { .. }));
+            }
+            result => panic!("unexpected content part: {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_input_schema() -> Result<()> {
+        let schema = serde_json::json!({
+            "type": "object",
+            "description": "A location lookup",
+            "properties": {
+                "unit": { "enum": ["celsius", "fahrenheit"] },
+                "location": { "$ref": "#/$defs/location" }
+            },
+            "required": ["location"],
+            "additionalProperties": false,
+            "$defs": { "location": { "type": "string" } }
+        });
+
+        let tool = serde_json::from_value::<Tool>(serde_json::json!({
+            "name": "get_weather",
+            "input_schema": schema,
+        }))?;
+        assert_eq!(serde_json::to_value(&tool.input_schema)?, schema);
+
+        assert!(ToolInputSchema::try_from(serde_json::json!({ "type": "string" })).is_err());
+        assert!(serde_json::from_value::<Tool>(serde_json::json!({
+            "name": "get_weather",
+            "input_schema": [],
+        }))
+        .is_err());
+
+        Ok(())
+    }
+
+    fn sse(events: &[&str]) -> Pin<Box<dyn Stream<Item = Result<Event>> + Send>> {
+        let body = events
+            .iter()
+            .map(|data| Ok(format!("event: message\ndata: {}\n\n", data).into_bytes()))
+            .collect::<Vec<_>>();
+
+        event_stream(futures::stream::iter(body), Some("req_01".into()))
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_errors() {
+        let message_start = r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-haiku-20240307","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#;
+
+        let events = sse(&[message_start, r#"{"type":"message_stop"}"#])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(Result::is_ok));
+
+        let events = sse(&[
+            message_start,
+            r#"{"type":"message_stop"}"#,
+            r#"{"type":"ping"}"#,
+        ])
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(Result::is_ok));
+
+        let events = sse(&[message_start]).collect::<Vec<_>>().await;
+        assert!(
+            matches!(&events[1], Err(Error::Stream(message)) if message.contains("message_stop"))
+        );
+
+        let events = sse(&[
+            message_start,
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        ])
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            Err(Error::Api(err)) => {
+                assert_eq!(err.kind, ApiErrorKind::Overloaded);
+                assert_eq!(err.request_id.as_deref(), Some("req_01"));
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+
+        let events = sse(&[r#"{"type":"content_block_delta","index":0"#])
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Err(Error::EventDecode { data, .. }) if data.contains("content_block_delta")
+        ));
+    }
+
+    #[test]
+    fn test_unknown_variants_are_preserved() -> Result<()> {
+        let response = serde_json::from_value::<MessageResponse>(serde_json::json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                { "type": "server_tool_use", "id": "srvtoolu_01", "name": "web_search", "input": {} },
+                { "type": "text", "text": "Searching." }
+            ],
+            "stop_reason": "pause_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 10, "output_tokens": 5 }
+        }))?;
+
+        assert!(matches!(
+            &response.stop_reason,
+            Some(StopReason::Unknown(reason)) if reason == "pause_turn"
+        ));
+        match &response.content[0] {
+            ContentPart::Unknown(value) => {
+                assert_eq!(value["type"], "server_tool_use");
+                assert_eq!(
+                    serde_json::to_value(&response.content[0])?["id"],
+                    "srvtoolu_01"
+                );
+            }
+            part => panic!("unexpected content part: {:?}", part),
+        }
+        assert!(matches!(response.content[1], ContentPart::Text { .. }));
+        assert_eq!(serde_json::to_value(StopReason::EndTurn)?, "end_turn");
+
+        let event = serde_json::from_str::<Event>(r#"{"type":"content_block_pause","index":0}"#)?;
+        assert!(matches!(event, Event::Unknown(value) if value["index"] == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_response_type() -> Result<()> {
+        let response = serde_json::from_value::<CreateMessageResponse>(serde_json::json!({
+            "id": "msg_01",
+            "type": "message_v2",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [{ "type": "text", "text": "Hi!" }],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": { "input_tokens": 10, "output_tokens": 5 }
+        }))?;
+        match response {
+            CreateMessageResponse::Unknown(value) => {
+                assert_eq!(value["type"], "message_v2");
+                assert_eq!(value["id"], "msg_01");
+                assert_eq!(
+                    Error::UnknownResponse(value).to_string(),
+                    r#"unrecognized response type "message_v2""#
+                );
+            }
+            response => panic!("unexpected response: {:?}", response),
+        }
+
+        assert!(matches!(
+            serde_json::from_str::<MessageResponseKind>(r#""message_v2""#)?,
+            MessageResponseKind::Unknown(kind) if kind == "message_v2"
+        ));
+        assert!(matches!(
+            serde_json::from_str::<MessageResponseKind>(r#""message""#)?,
+            MessageResponseKind::Message
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_known_variants_fail() -> Result<()> {
+        assert!(
+            serde_json::from_str::<Event>(r#"{"type":"message_start","message":{"id":"x"}}"#)
+                .is_err()
+        );
+        assert!(serde_json::from_str::<ContentPart>(r#"{"type":"tool_result"}"#).is_err());
+        assert!(serde_json::from_str::<MessageResponse>(
+            r#"{"id":"msg_01","type":"message","role":"assistant","model":"m","content":[{"type":"text"}],"stop_reason":null,"stop_sequence":null,"usage":{"output_tokens":1}}"#
+        )
+        .is_err());
+        let part = serde_json::from_str::<ContentPart>(
+            r#"{"type":"tool_result","tool_use_id":"x","content":"ok"}"#,
+        )?;
+        assert!(matches!(part, ContentPart::ToolResult { .. }));
+        let part =
+            serde_json::from_str::<ContentPart>(r#"{"type":"tool_result","tool_use_id":"x"}"#)?;
+        assert!(matches!(
+            part,
+            ContentPart::ToolResult { content: Content::Single(text), .. } if text.is_empty()
+        ));
+
+        Ok(())
     }
 }
@@ -0,0 +1,459 @@
+use crate::{
+    error::{Error, Result},
+    messages::{Content, ContentPart, CreateMessageRequest, ThinkingConfig},
+};
+
+const MIN_THINKING_BUDGET_TOKENS: u32 = 1024;
+
+/// Defines an open-ended model id newtype with `from_static`/`new`/`id`
+/// constructors, `Display`, `FromStr` and `From` conversions and transparent
+/// serde impls, so each provider's `Model` behaves the same way. Provider
+/// crates invoke it through `anthropic::__private::model_type!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __model_type {
+    ($name:ident) => {
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(::std::borrow::Cow<'static, str>);
+
+        impl $name {
+            pub const fn from_static(id: &'static str) -> Self {
+                Self(::std::borrow::Cow::Borrowed(id))
+            }
+
+            pub fn new<S>(id: S) -> Self
+            where
+                S: Into<String>,
+            {
+                Self(::std::borrow::Cow::Owned(id.into()))
+            }
+
+            pub fn id(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl $crate::__private::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: $crate::__private::serde::Serializer,
+            {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: $crate::__private::serde::Deserializer<'de>,
+            {
+                <String as $crate::__private::serde::Deserialize>::deserialize(deserializer)
+                    .map($name::new)
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::Error;
+
+            fn from_str(s: &str) -> $crate::Result<Self> {
+                match s.trim() {
+                    "" => Err($crate::Error::validation("model id must not be empty")),
+                    id => Ok($name::new(id)),
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                $name::new(id)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name::new(id)
+            }
+        }
+    };
+}
+
+__model_type!(Model);
+
+impl Model {
+    pub const CLAUDE_OPUS_4_20250514: Model = Model::from_static("claude-opus-4-20250514");
+    pub const CLAUDE_SONNET_4_20250514: Model = Model::from_static("claude-sonnet-4-20250514");
+    pub const CLAUDE_3_7_SONNET_LATEST: Model = Model::from_static("claude-3-7-sonnet-latest");
+    pub const CLAUDE_3_7_SONNET_20250219: Model = Model::from_static("claude-3-7-sonnet-20250219");
+    pub const CLAUDE_3_5_SONNET_LATEST: Model = Model::from_static("claude-3-5-sonnet-latest");
+    pub const CLAUDE_3_5_SONNET_20241022: Model = Model::from_static("claude-3-5-sonnet-20241022");
+    pub const CLAUDE_3_5_SONNET_20240620: Model = Model::from_static("claude-3-5-sonnet-20240620");
+    pub const CLAUDE_3_5_HAIKU_LATEST: Model = Model::from_static("claude-3-5-haiku-latest");
+    pub const CLAUDE_3_5_HAIKU_20241022: Model = Model::from_static("claude-3-5-haiku-20241022");
+    pub const CLAUDE_3_OPUS_LATEST: Model = Model::from_static("claude-3-opus-latest");
+    pub const CLAUDE_3_OPUS_20240229: Model = Model::from_static("claude-3-opus-20240229");
+    pub const CLAUDE_3_SONNET_20240229: Model = Model::from_static("claude-3-sonnet-20240229");
+    pub const CLAUDE_3_HAIKU_20240307: Model = Model::from_static("claude-3-haiku-20240307");
+
+    pub fn capabilities(&self) -> Option<&'static ModelCapabilities> {
+        self.known().map(|known| &known.capabilities)
+    }
+
+    pub fn canonical(&self) -> Option<Model> {
+        self.known().map(|known| Model::from_static(known.ids[0]))
+    }
+
+    /// The id of this model on `provider`, if it's a known model.
+    pub fn provider_id(&self, provider: Provider) -> Option<&'static str> {
+        self.known().map(|known| known.provider_id(provider))
+    }
+
+    /// The canonical model behind a `provider` specific id.
+    pub fn from_provider_id(provider: Provider, id: &str) -> Option<Model> {
+        KNOWN_MODELS
+            .iter()
+            .find(|known| match provider {
+                Provider::Anthropic => known.ids.contains(&id),
+                provider => known.provider_id(provider) == id,
+            })
+            .map(|known| Model::from_static(known.ids[0]))
+    }
+
+    fn known(&self) -> Option<&'static KnownModel> {
+        KNOWN_MODELS
+            .iter()
+            .find(|known| known.ids.contains(&self.id()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Provider {
+    Anthropic,
+    Bedrock,
+    VertexAi,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    pub vision: bool,
+    pub tools: bool,
+    pub thinking: bool,
+    pub deprecated_at: Option<&'static str>,
+}
+
+impl ModelCapabilities {
+    pub fn validate(&self, request: &CreateMessageRequest) -> Result<()> {
+        if request.max_tokens > self.max_output_tokens {
+            return Err(Error::validation(format!(
+                "max_tokens {} exceeds the {} output tokens supported by {}",
+                request.max_tokens, self.max_output_tokens, request.model
+            )));
+        }
+
+        if !self.vision && has_images(request) {
+            return Err(Error::validation(format!(
+                "{} does not support image input",
+                request.model
+            )));
+        }
+
+        if let Some(budget_tokens) = request
+            .thinking
+            .as_ref()
+            .and_then(ThinkingConfig::budget_tokens)
+        {
+            if !self.thinking {
+                return Err(Error::validation(format!(
+                    "{} does not support extended thinking",
+                    request.model
+                )));
+            }
+
+            if budget_tokens < MIN_THINKING_BUDGET_TOKENS || budget_tokens >= request.max_tokens {
+                return Err(Error::validation(format!(
+                    "thinking budget_tokens must be at least {} and less than max_tokens",
+                    MIN_THINKING_BUDGET_TOKENS
+                )));
+            }
+        }
+
+        if !self.tools && request.tools.is_some() {
+            return Err(Error::validation(format!(
+                "{} does not support tool use",
+                request.model
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn has_images(request: &CreateMessageRequest) -> bool {
+    request
+        .messages
+        .iter()
+        .any(|message| content_has_images(&message.content))
+}
+
+fn content_has_images(content: &Content) -> bool {
+    match content {
+        Content::Multi(parts) => parts.iter().any(|part| match part {
+            ContentPart::Image { .. } => true,
+            ContentPart::ToolResult { content, .. } => content_has_images(content),
+            _ => false,
+        }),
+        Content::Single(_) => false,
+    }
+}
+
+const fn capabilities(
+    max_output_tokens: u32,
+    thinking: bool,
+    deprecated_at: Option<&'static str>,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        context_window: 200_000,
+        max_output_tokens,
+        vision: true,
+        tools: true,
+        thinking,
+        deprecated_at,
+    }
+}
+
+struct KnownModel {
+    ids: &'static [&'static str],
+    bedrock: &'static str,
+    vertex_ai: &'static str,
+    capabilities: ModelCapabilities,
+}
+
+impl KnownModel {
+    fn provider_id(&self, provider: Provider) -> &'static str {
+        match provider {
+            Provider::Anthropic => self.ids[0],
+            Provider::Bedrock => self.bedrock,
+            Provider::VertexAi => self.vertex_ai,
+        }
+    }
+}
+
+/// Keyed by the Anthropic id; the first id is canonical, the rest are aliases.
+const KNOWN_MODELS: &[KnownModel] = &[
+    KnownModel {
+        ids: &["claude-opus-4-20250514", "claude-opus-4-0"],
+        bedrock: "anthropic.claude-opus-4-20250514-v1:0",
+        vertex_ai: "claude-opus-4@20250514",
+        capabilities: capabilities(32_000, true, None),
+    },
+    KnownModel {
+        ids: &["claude-sonnet-4-20250514", "claude-sonnet-4-0"],
+        bedrock: "anthropic.claude-sonnet-4-20250514-v1:0",
+        vertex_ai: "claude-sonnet-4@20250514",
+        capabilities: capabilities(64_000, true, None),
+    },
+    KnownModel {
+        ids: &["claude-3-7-sonnet-20250219", "claude-3-7-sonnet-latest"],
+        bedrock: "anthropic.claude-3-7-sonnet-20250219-v1:0",
+        vertex_ai: "claude-3-7-sonnet@20250219",
+        capabilities: capabilities(64_000, true, None),
+    },
+    KnownModel {
+        ids: &["claude-3-5-sonnet-20241022", "claude-3-5-sonnet-latest"],
+        bedrock: "anthropic.claude-3-5-sonnet-20241022-v2:0",
+        vertex_ai: "claude-3-5-sonnet-v2@20241022",
+        capabilities: capabilities(8_192, false, Some("2025-08-13")),
+    },
+    KnownModel {
+        ids: &["claude-3-5-sonnet-20240620"],
+        bedrock: "anthropic.claude-3-5-sonnet-20240620-v1:0",
+        vertex_ai: "claude-3-5-sonnet@20240620",
+        capabilities: capabilities(8_192, false, Some("2025-08-13")),
+    },
+    KnownModel {
+        ids: &["claude-3-5-haiku-20241022", "claude-3-5-haiku-latest"],
+        bedrock: "anthropic.claude-3-5-haiku-20241022-v1:0",
+        vertex_ai: "claude-3-5-haiku@20241022",
+        capabilities: capabilities(8_192, false, None),
+    },
+    KnownModel {
+        ids: &["claude-3-opus-20240229", "claude-3-opus-latest"],
+        bedrock: "anthropic.claude-3-opus-20240229-v1:0",
+        vertex_ai: "claude-3-opus@20240229",
+        capabilities: capabilities(4_096, false, Some("2025-06-30")),
+    },
+    KnownModel {
+        ids: &["claude-3-sonnet-20240229"],
+        bedrock: "anthropic.claude-3-sonnet-20240229-v1:0",
+        vertex_ai: "claude-3-sonnet@20240229",
+        capabilities: capabilities(4_096, false, Some("2025-01-21")),
+    },
+    KnownModel {
+        ids: &["claude-3-haiku-20240307"],
+        bedrock: "anthropic.claude-3-haiku-20240307-v1:0",
+        vertex_ai: "claude-3-haiku@20240307",
+        capabilities: capabilities(4_096, false, None),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::messages::{ImageSource, Message};
+
+    use super::*;
+
+    #[test]
+    fn test_unknown_models_are_accepted() -> Result<()> {
+        let model = "claude-next-20990101".parse::<Model>()?;
+
+        assert_eq!(model.to_string(), "claude-next-20990101");
+        assert!(model.capabilities().is_none());
+        assert!("  ".parse::<Model>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde_is_transparent() -> Result<()> {
+        let value = serde_json::to_value(&Model::CLAUDE_SONNET_4_20250514)?;
+
+        assert_eq!(value, serde_json::json!("claude-sonnet-4-20250514"));
+        assert_eq!(
+            serde_json::from_value::<Model>(value)?,
+            Model::CLAUDE_SONNET_4_20250514
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_resolves_aliases() {
+        assert_eq!(
+            Model::CLAUDE_3_5_SONNET_LATEST.canonical(),
+            Some(Model::CLAUDE_3_5_SONNET_20241022)
+        );
+        assert_eq!(
+            Model::CLAUDE_3_HAIKU_20240307.canonical(),
+            Some(Model::CLAUDE_3_HAIKU_20240307)
+        );
+        assert_eq!(Model::from("claude-next").canonical(), None);
+    }
+
+    #[test]
+    fn test_provider_ids() -> Result<()> {
+        assert_eq!(
+            Model::CLAUDE_3_5_SONNET_LATEST.provider_id(Provider::Bedrock),
+            Some("anthropic.claude-3-5-sonnet-20241022-v2:0")
+        );
+        assert_eq!(
+            Model::CLAUDE_3_5_SONNET_20241022.provider_id(Provider::VertexAi),
+            Some("claude-3-5-sonnet-v2@20241022")
+        );
+        assert_eq!(
+            Model::from_provider_id(Provider::VertexAi, "claude-3-haiku@20240307"),
+            Some(Model::CLAUDE_3_HAIKU_20240307)
+        );
+        assert_eq!(
+            Model::from_provider_id(Provider::Anthropic, "claude-3-opus-latest"),
+            Some(Model::CLAUDE_3_OPUS_20240229)
+        );
+        assert_eq!(
+            Model::from("claude-next").provider_id(Provider::Bedrock),
+            None
+        );
+
+        let model = serde_json::from_str::<Model>(r#""claude-3-7-sonnet-latest""#)?;
+        assert_eq!(model, Model::CLAUDE_3_7_SONNET_LATEST);
+        assert_eq!(
+            serde_json::to_string(&model)?,
+            r#""claude-3-7-sonnet-latest""#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities_validate_max_tokens() -> Result<()> {
+        let capabilities = Model::CLAUDE_3_HAIKU_20240307.capabilities().unwrap();
+        let request = CreateMessageRequest::builder()
+            .model("claude-3-haiku-20240307")
+            .messages(vec![Message::user("Hi!".into())])
+            .max_tokens(4096)
+            .build()?;
+        assert!(capabilities.validate(&request).is_ok());
+
+        let err = CreateMessageRequest::builder()
+            .model(Model::CLAUDE_3_HAIKU_20240307)
+            .messages(vec![Message::user("Hi!".into())])
+            .max_tokens(8192)
+            .validate_capabilities()
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities_validate_tool_result_images() {
+        let capabilities = ModelCapabilities {
+            vision: false,
+            ..Model::CLAUDE_3_HAIKU_20240307
+                .capabilities()
+                .unwrap()
+                .clone()
+        };
+        let request = CreateMessageRequest::builder()
+            .model("claude-3-haiku-20240307")
+            .messages(vec![Message::user(Content::Multi(vec![
+                ContentPart::tool_result(
+                    "toolu_01",
+                    Content::Multi(vec![ContentPart::Image {
+                        source: ImageSource::url("https://example.com/chart.png"),
+                        cache_control: None,
+                    }]),
+                ),
+            ]))])
+            .max_tokens(1024)
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            capabilities.validate(&request),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_capabilities_validate_thinking() {
+        let request = |model: Model, budget_tokens| {
+            CreateMessageRequest::builder()
+                .model(model)
+                .messages(vec![Message::user("Hi!".into())])
+                .max_tokens(4096)
+                .thinking(ThinkingConfig::enabled(budget_tokens))
+                .validate_capabilities()
+                .build()
+        };
+
+        assert!(request(Model::CLAUDE_3_7_SONNET_20250219, 2048).is_ok());
+        assert!(request(Model::CLAUDE_3_7_SONNET_20250219, 512).is_err());
+        assert!(request(Model::CLAUDE_3_7_SONNET_20250219, 4096).is_err());
+        assert!(request(Model::CLAUDE_3_HAIKU_20240307, 2048).is_err());
+
+        assert!(CreateMessageRequest::builder()
+            .model(Model::CLAUDE_3_HAIKU_20240307)
+            .messages(vec![Message::user("Hi!".into())])
+            .max_tokens(4096)
+            .thinking(ThinkingConfig::enabled(2048))
+            .build()
+            .is_ok());
+    }
+}
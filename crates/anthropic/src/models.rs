@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use http_client::http::Method;
+
+use crate::{
+    error::Result,
+    pagination::{Page, PageParams},
+    path_segment, Anthropic,
+};
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: String,
+    pub created_at: String,
+}
+
+#[async_trait]
+pub trait Models: Send + Sync {
+    async fn list_models(&self, params: PageParams) -> Result<Page<ModelInfo>>;
+
+    async fn retrieve_model(&self, model_id: &str) -> Result<ModelInfo>;
+}
+
+#[async_trait]
+impl Models for Anthropic {
+    async fn list_models(&self, params: PageParams) -> Result<Page<ModelInfo>> {
+        self.send_json(Method::GET, format!("/v1/models{}", params.query()), None)
+            .await
+    }
+
+    async fn retrieve_model(&self, model_id: &str) -> Result<ModelInfo> {
+        self.send_json(
+            Method::GET,
+            format!("/v1/models/{}", path_segment(model_id)),
+            None,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_page() -> Result<()> {
+        let page = serde_json::from_str::<Page<ModelInfo>>(
+            r#"{"data":[{"type":"model","id":"claude-3-5-sonnet-20241022","display_name":"Claude 3.5 Sonnet (New)","created_at":"2024-10-22T00:00:00Z"}],"has_more":true,"first_id":"claude-3-5-sonnet-20241022","last_id":"claude-3-5-sonnet-20241022"}"#,
+        )?;
+
+        assert_eq!(page.data[0].display_name, "Claude 3.5 Sonnet (New)");
+        assert_eq!(
+            page.next_page_params(None).unwrap().after_id.as_deref(),
+            Some("claude-3-5-sonnet-20241022")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub has_more: bool,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn next_page_params(&self, limit: Option<u32>) -> Option<PageParams> {
+        match (self.has_more, &self.last_id) {
+            (true, Some(last_id)) => Some(PageParams {
+                after_id: Some(last_id.to_owned()),
+                before_id: None,
+                limit,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PageParams {
+    pub after_id: Option<String>,
+    pub before_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+impl PageParams {
+    pub fn after_id<S>(mut self, after_id: S) -> Self
+    where
+        S: ToString,
+    {
+        self.after_id = Some(after_id.to_string());
+        self
+    }
+
+    pub fn before_id<S>(mut self, before_id: S) -> Self
+    where
+        S: ToString,
+    {
+        self.before_id = Some(before_id.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub(crate) fn query(&self) -> String {
+        let params = [
+            ("after_id", self.after_id.to_owned()),
+            ("before_id", self.before_id.to_owned()),
+            ("limit", self.limit.map(|limit| limit.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+        .collect::<Vec<_>>();
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            let query = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params)
+                .finish();
+            format!("?{}", query)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_params_query() {
+        assert_eq!(PageParams::default().query(), "");
+        assert_eq!(
+            PageParams::default()
+                .after_id("msgbatch_01")
+                .limit(10)
+                .query(),
+            "?after_id=msgbatch_01&limit=10"
+        );
+        assert_eq!(
+            PageParams::default().before_id("a&b=c d#e").query(),
+            "?before_id=a%26b%3Dc+d%23e"
+        );
+    }
+
+    #[test]
+    fn test_next_page_params() {
+        let page = Page::<()> {
+            data: vec![],
+            has_more: true,
+            first_id: Some("a".into()),
+            last_id: Some("b".into()),
+        };
+
+        let params = page.next_page_params(Some(5)).unwrap();
+        assert_eq!(params.after_id.as_deref(), Some("b"));
+        assert_eq!(params.limit, Some(5));
+
+        let last_page = Page {
+            has_more: false,
+            ..page
+        };
+        assert!(last_page.next_page_params(None).is_none());
+    }
+}
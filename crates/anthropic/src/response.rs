@@ -0,0 +1,230 @@
+use std::time::{Duration, SystemTime};
+
+use futures::{AsyncReadExt, Stream};
+use http_client::{
+    http::{HeaderMap, Response, StatusCode},
+    AsyncBody, ResponseAsyncBodyExt,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{ApiError, ApiErrorKind, Error, Result},
+    messages::ErrorResponse,
+    retry,
+};
+
+const REQUEST_ID_HEADER: &str = "request-id";
+const RATE_LIMIT_HEADER_PREFIX: &str = "anthropic-ratelimit";
+
+#[derive(Debug)]
+pub struct RawResponse<T> {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub request_id: Option<String>,
+    pub rate_limit: RateLimitInfo,
+    pub data: T,
+}
+
+impl<T> RawResponse<T> {
+    pub fn from_parts(status: StatusCode, headers: HeaderMap, data: T) -> Self {
+        Self {
+            status,
+            request_id: request_id(&headers),
+            rate_limit: RateLimitInfo::from_headers(&headers),
+            headers,
+            data,
+        }
+    }
+
+    pub fn map<U, F>(self, f: F) -> RawResponse<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        RawResponse {
+            status: self.status,
+            headers: self.headers,
+            request_id: self.request_id,
+            rate_limit: self.rate_limit,
+            data: f(self.data),
+        }
+    }
+
+    pub fn into_data(self) -> T {
+        self.data
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    pub requests: RateLimit,
+    pub tokens: RateLimit,
+    pub input_tokens: RateLimit,
+    pub output_tokens: RateLimit,
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitInfo {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let rate_limit = |kind: &str| {
+            let header = |field: &str| {
+                headers
+                    .get(format!("{}-{}-{}", RATE_LIMIT_HEADER_PREFIX, kind, field))
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::trim)
+            };
+
+            RateLimit {
+                limit: header("limit").and_then(|value| value.parse().ok()),
+                remaining: header("remaining").and_then(|value| value.parse().ok()),
+                reset: header("reset").and_then(|value| humantime::parse_rfc3339(value).ok()),
+            }
+        };
+
+        Self {
+            requests: rate_limit("requests"),
+            tokens: rate_limit("tokens"),
+            input_tokens: rate_limit("input-tokens"),
+            output_tokens: rate_limit("output-tokens"),
+            retry_after: retry::retry_after(headers),
+        }
+    }
+}
+
+pub fn request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string)
+}
+
+pub fn api_error(status: Option<u16>, request_id: Option<String>, body: &str) -> ApiError {
+    match serde_json::from_str::<ErrorResponse>(body) {
+        Ok(ErrorResponse { error }) => ApiError {
+            status,
+            kind: error.kind,
+            message: error.message,
+            request_id,
+        },
+        Err(_) => ApiError {
+            status,
+            kind: status
+                .map(ApiErrorKind::from_status)
+                .unwrap_or(ApiErrorKind::Api),
+            message: match body.trim() {
+                "" => "empty response body".into(),
+                body => body.to_string(),
+            },
+            request_id,
+        },
+    }
+}
+
+pub async fn error_for_status(mut response: Response<AsyncBody>) -> Result<Response<AsyncBody>> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let request_id = request_id(response.headers());
+    let body = response.text().await.map_err(Error::transport)?;
+
+    Err(api_error(Some(status.as_u16()), request_id, &body).into())
+}
+
+pub async fn read_json<T>(response: Response<AsyncBody>) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let mut response = error_for_status(response).await?;
+    let text = response.text().await.map_err(Error::transport)?;
+
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub fn body_stream(body: AsyncBody) -> impl Stream<Item = std::io::Result<Vec<u8>>> + Send {
+    futures::stream::try_unfold(body, |mut body| async move {
+        let mut buf = vec![0; 8 * 1024];
+        let read = body.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        buf.truncate(read);
+        Ok(Some((buf, body)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use http_client::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_info_from_headers() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("anthropic-ratelimit-requests-limit", "50"),
+            ("anthropic-ratelimit-requests-remaining", "49"),
+            ("anthropic-ratelimit-requests-reset", "2024-10-16T12:00:00Z"),
+            ("anthropic-ratelimit-input-tokens-limit", "40000"),
+            ("anthropic-ratelimit-input-tokens-remaining", "not-a-number"),
+            ("retry-after", "7"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+
+        let info = RateLimitInfo::from_headers(&headers);
+
+        assert_eq!(info.requests.limit, Some(50));
+        assert_eq!(info.requests.remaining, Some(49));
+        assert_eq!(
+            info.requests.reset,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_729_080_000))
+        );
+        assert_eq!(info.input_tokens.limit, Some(40000));
+        assert_eq!(info.input_tokens.remaining, None);
+        assert_eq!(info.output_tokens, RateLimit::default());
+        assert_eq!(info.retry_after, Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn test_api_error_from_envelope() {
+        let err = api_error(
+            Some(529),
+            Some("req_123".into()),
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+
+        assert_eq!(err.status, Some(529));
+        assert_eq!(err.kind, ApiErrorKind::Overloaded);
+        assert_eq!(err.message, "Overloaded");
+        assert_eq!(err.request_id.as_deref(), Some("req_123"));
+    }
+
+    #[test]
+    fn test_api_error_from_html() {
+        let err = api_error(Some(529), None, "<html><body>Overloaded</body></html>");
+
+        assert_eq!(err.kind, ApiErrorKind::Overloaded);
+        assert_eq!(err.message, "<html><body>Overloaded</body></html>");
+    }
+
+    #[test]
+    fn test_api_error_from_unknown_type() {
+        let err = api_error(
+            Some(400),
+            None,
+            r#"{"type":"error","error":{"type":"billing_error","message":"No credits"}}"#,
+        );
+
+        assert_eq!(err.kind, ApiErrorKind::Other("billing_error".into()));
+    }
+}
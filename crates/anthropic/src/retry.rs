@@ -0,0 +1,472 @@
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use futures_timer::Delay;
+use http_client::{
+    http::{header::RETRY_AFTER, HeaderMap, Request, Response},
+    AsyncBody, HttpClient,
+};
+
+use crate::error::{Error, Result};
+
+const RETRY_AFTER_MS_HEADER: &str = "retry-after-ms";
+const SHOULD_RETRY_HEADER: &str = "x-should-retry";
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: self.clone(),
+            attempt: 0,
+        }
+    }
+
+    pub async fn send<F, Fut>(
+        &self,
+        http_client: &dyn HttpClient,
+        make_request: F,
+    ) -> Result<Response<AsyncBody>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Request<AsyncBody>>>,
+    {
+        self.backoff().send(http_client, make_request).await
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        delay.mul_f64(1.0 - fastrand::f64() * 0.25)
+    }
+}
+
+pub struct Backoff {
+    policy: RetryPolicy,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub async fn wait(&mut self, retry_after: Option<Duration>) -> bool {
+        if self.attempt >= self.policy.max_retries {
+            return false;
+        }
+
+        Delay::new(retry_after.unwrap_or_else(|| self.policy.delay(self.attempt))).await;
+        self.attempt += 1;
+
+        true
+    }
+
+    pub async fn send<F, Fut>(
+        &mut self,
+        http_client: &dyn HttpClient,
+        make_request: F,
+    ) -> Result<Response<AsyncBody>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Request<AsyncBody>>>,
+    {
+        loop {
+            match http_client.send(make_request().await?).await {
+                Ok(response) => {
+                    if !should_retry(&response) || !self.wait(retry_after(response.headers())).await
+                    {
+                        return Ok(response);
+                    }
+                }
+                Err(err) => {
+                    if !self.wait(None).await {
+                        return Err(Error::transport(err));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 409 | 429) || status >= 500
+}
+
+fn should_retry(response: &Response<AsyncBody>) -> bool {
+    match response
+        .headers()
+        .get(SHOULD_RETRY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some("true") => true,
+        Some("false") => false,
+        _ => is_retryable_status(response.status().as_u16()),
+    }
+}
+
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    retry_after_at(headers, SystemTime::now())
+}
+
+fn retry_after_at(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let seconds = |value: &str, scale: f64| {
+        value
+            .parse::<f64>()
+            .ok()
+            .and_then(|value| Duration::try_from_secs_f64(value / scale).ok())
+    };
+
+    header(RETRY_AFTER_MS_HEADER)
+        .and_then(|millis| seconds(millis, 1000.0))
+        .or_else(|| {
+            let value = header(RETRY_AFTER.as_str())?;
+            // Retry-After is either delay-seconds or an HTTP-date (RFC 9110).
+            seconds(value, 1.0).or_else(|| {
+                let date = httpdate::parse_http_date(value).ok()?;
+                Some(date.duration_since(now).unwrap_or_default())
+            })
+        })
+        .filter(|delay| *delay <= MAX_RETRY_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use futures::{future::BoxFuture, FutureExt, StreamExt};
+    use http_client::http::{HeaderValue, Method};
+
+    use crate::{
+        error::{ApiError, ApiErrorKind},
+        messages::{
+            CreateMessageRequest, CreateMessageRequestWithStream, Event, Message,
+            MessagesWithRawResponse, Requester,
+        },
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct ScriptedHttpClient {
+        responses: Mutex<Vec<Response<AsyncBody>>>,
+        requests: Mutex<u32>,
+    }
+
+    impl ScriptedHttpClient {
+        fn new(responses: Vec<Response<AsyncBody>>) -> Arc<Self> {
+            Arc::new(Self {
+                responses: Mutex::new(responses.into_iter().rev().collect()),
+                ..Default::default()
+            })
+        }
+
+        fn requests(&self) -> u32 {
+            *self.requests.lock().unwrap()
+        }
+    }
+
+    impl HttpClient for ScriptedHttpClient {
+        fn send(
+            &self,
+            _: Request<AsyncBody>,
+        ) -> BoxFuture<'static, std::result::Result<Response<AsyncBody>, anyhow::Error>> {
+            *self.requests.lock().unwrap() += 1;
+            let response = self
+                .responses
+                .lock()
+                .unwrap()
+                .pop()
+                .expect("no scripted response left");
+
+            async move { Ok(response) }.boxed()
+        }
+    }
+
+    #[async_trait]
+    impl Requester for Arc<ScriptedHttpClient> {
+        fn http_client(&self) -> Arc<dyn HttpClient> {
+            self.clone()
+        }
+
+        fn base_url(&self) -> String {
+            "https://api.anthropic.com".into()
+        }
+
+        fn endpoint_url(&self, _: &CreateMessageRequestWithStream) -> String {
+            "/v1/messages".into()
+        }
+
+        fn retry_policy(&self) -> RetryPolicy {
+            policy()
+        }
+
+        async fn request_builder(
+            &self,
+            url: String,
+            _: CreateMessageRequestWithStream,
+        ) -> Result<Request<AsyncBody>> {
+            request(url).await
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_initial_delay(Duration::from_millis(1))
+            .with_max_delay(Duration::from_millis(1))
+    }
+
+    async fn request(url: String) -> Result<Request<AsyncBody>> {
+        Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .body(AsyncBody::default())
+            .map_err(Error::validation)
+    }
+
+    fn reply(status: u16, headers: &[(&str, &str)], body: &str) -> Response<AsyncBody> {
+        let mut response = Response::builder().status(status);
+        for (name, value) in headers {
+            response = response.header(*name, *value);
+        }
+
+        response.body(AsyncBody::from(body.to_string())).unwrap()
+    }
+
+    async fn send(
+        policy: RetryPolicy,
+        http_client: &ScriptedHttpClient,
+    ) -> Result<Response<AsyncBody>> {
+        policy
+            .send(http_client, || {
+                request("https://api.anthropic.com/v1/messages".into())
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_retryable_statuses() -> Result<()> {
+        let http_client = ScriptedHttpClient::new(vec![
+            reply(429, &[("retry-after-ms", "1")], ""),
+            reply(529, &[], ""),
+            reply(200, &[], "{}"),
+        ]);
+
+        let response = send(policy(), &http_client).await?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(http_client.requests(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_honours_should_retry_header() -> Result<()> {
+        let http_client = ScriptedHttpClient::new(vec![
+            reply(503, &[(SHOULD_RETRY_HEADER, "false")], ""),
+            reply(200, &[], "{}"),
+        ]);
+
+        let response = send(policy(), &http_client).await?;
+        assert_eq!(response.status(), 503);
+        assert_eq!(http_client.requests(), 1);
+
+        let http_client = ScriptedHttpClient::new(vec![
+            reply(400, &[(SHOULD_RETRY_HEADER, "true")], ""),
+            reply(200, &[], "{}"),
+        ]);
+
+        let response = send(policy(), &http_client).await?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(http_client.requests(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_gives_up_after_max_retries() -> Result<()> {
+        let http_client = ScriptedHttpClient::new(vec![
+            reply(500, &[], ""),
+            reply(500, &[], ""),
+            reply(500, &[], ""),
+            reply(200, &[], "{}"),
+        ]);
+
+        let response = send(policy().with_max_retries(2), &http_client).await?;
+        assert_eq!(response.status(), 500);
+        assert_eq!(http_client.requests(), 3);
+
+        let http_client = ScriptedHttpClient::new(vec![reply(500, &[], "")]);
+
+        let response = send(RetryPolicy::none(), &http_client).await?;
+        assert_eq!(response.status(), 500);
+        assert_eq!(http_client.requests(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_retries_only_before_first_event() -> Result<()> {
+        let sse = |events: &[&str]| {
+            events
+                .iter()
+                .map(|data| format!("data: {}\n\n", data))
+                .collect::<String>()
+        };
+        let message_start = r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-haiku-20240307","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#;
+        let overloaded =
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+
+        let http_client = ScriptedHttpClient::new(vec![
+            reply(200, &[], &sse(&[overloaded])),
+            reply(200, &[], &sse(&[message_start, overloaded])),
+            reply(
+                200,
+                &[],
+                &sse(&[message_start, r#"{"type":"message_stop"}"#]),
+            ),
+        ]);
+        let request = CreateMessageRequest::builder()
+            .model("claude-3-haiku-20240307")
+            .messages(vec![Message::user("Hi!".into())])
+            .max_tokens(100)
+            .build()?;
+
+        let events = http_client
+            .messages_stream_with_raw_response(request)
+            .await?
+            .into_data()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(http_client.requests(), 2);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Ok(Event::MessageStart { .. })));
+        assert!(matches!(
+            &events[1],
+            Err(Error::Api(ApiError {
+                kind: ApiErrorKind::Overloaded,
+                ..
+            }))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+
+        headers.insert(RETRY_AFTER_MS_HEADER, HeaderValue::from_static("150"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(150)));
+
+        headers.remove(RETRY_AFTER_MS_HEADER);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3600"));
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("-1"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_retry_after_http_date() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:30 GMT"),
+        );
+        assert_eq!(retry_after_at(&headers, now), Some(Duration::from_secs(30)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:27:00 GMT"),
+        );
+        assert_eq!(retry_after_at(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 08:28:00 GMT"),
+        );
+        assert_eq!(retry_after_at(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after_at(&headers, now), None);
+    }
+
+    #[test]
+    fn test_is_retryable_status() {
+        for status in [408, 409, 429, 500, 502, 503, 529] {
+            assert!(is_retryable_status(status), "{status}");
+        }
+
+        for status in [200, 400, 401, 403, 404, 413] {
+            assert!(!is_retryable_status(status), "{status}");
+        }
+    }
+
+    #[test]
+    fn test_delay_is_bounded() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 0..10 {
+            let delay = policy.delay(attempt);
+            assert!(delay <= policy.max_delay);
+            assert!(delay >= policy.initial_delay.mul_f64(0.75));
+        }
+    }
+}
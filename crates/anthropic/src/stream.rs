@@ -0,0 +1,772 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_stream::stream;
+use futures::{AsyncRead, Stream, StreamExt, TryStreamExt};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{ApiError, Error, Result},
+    messages::{ContentPart, Event, MessageResponse},
+};
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
+
+#[derive(Clone, Debug)]
+pub enum StreamEvent {
+    Text {
+        index: usize,
+        text: String,
+    },
+    Thinking {
+        index: usize,
+        thinking: String,
+    },
+    PartialToolInput {
+        index: usize,
+        partial_json: String,
+        input: Value,
+    },
+    ToolInput {
+        index: usize,
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ContentBlockStop {
+        index: usize,
+        content_block: ContentPart,
+    },
+    MessageStop,
+}
+
+pub struct MessageStream {
+    events: EventStream,
+    snapshot: Option<MessageResponse>,
+    partial_json: HashMap<usize, PartialJson>,
+    pending: VecDeque<StreamEvent>,
+}
+
+impl MessageStream {
+    pub fn new(events: EventStream) -> Self {
+        Self {
+            events,
+            snapshot: None,
+            partial_json: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<&MessageResponse> {
+        self.snapshot.as_ref()
+    }
+
+    pub async fn final_message(mut self) -> Result<MessageResponse> {
+        while let Some(event) = self.next().await {
+            event?;
+        }
+
+        self.snapshot
+            .ok_or_else(|| Error::Stream("stream ended before message_start".into()))
+    }
+
+    fn apply(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Ping | Event::Unknown(_) => {}
+            Event::MessageStart { message } => {
+                self.snapshot = Some(message.message_response);
+            }
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                let content = &mut snapshot_mut(&mut self.snapshot)?.content;
+                let index = index as usize;
+                if index < content.len() {
+                    content[index] = content_block;
+                } else {
+                    content.push(content_block);
+                }
+            }
+            Event::ContentBlockDelta { index, delta } => {
+                let index = index as usize;
+                if let ContentPart::InputJsonDelta { partial_json } = delta {
+                    let buffer = self.partial_json.entry(index).or_default();
+                    buffer.push(&partial_json);
+
+                    if let Some(value) = buffer.value() {
+                        if let ContentPart::ToolUse { input, .. } =
+                            content_block_mut(&mut self.snapshot, index)?
+                        {
+                            *input = value.to_owned();
+                        }
+                        self.pending.push_back(StreamEvent::PartialToolInput {
+                            index,
+                            partial_json,
+                            input: value,
+                        });
+                    }
+                    return Ok(());
+                }
+
+                if let ContentPart::Unknown(_) = delta {
+                    return Ok(());
+                }
+
+                let block = content_block_mut(&mut self.snapshot, index)?;
+                match (block, delta) {
+                    (ContentPart::Text { text, .. }, ContentPart::TextDelta { text: delta }) => {
+                        text.push_str(&delta);
+                        self.pending
+                            .push_back(StreamEvent::Text { index, text: delta });
+                    }
+                    (
+                        ContentPart::Text { citations, .. },
+                        ContentPart::CitationsDelta { citation },
+                    ) => citations.get_or_insert_with(Vec::new).push(citation),
+                    (
+                        ContentPart::Thinking { thinking, .. },
+                        ContentPart::ThinkingDelta { thinking: delta },
+                    ) => {
+                        thinking.push_str(&delta);
+                        self.pending.push_back(StreamEvent::Thinking {
+                            index,
+                            thinking: delta,
+                        });
+                    }
+                    (
+                        ContentPart::Thinking { signature, .. },
+                        ContentPart::SignatureDelta { signature: delta },
+                    ) => signature.push_str(&delta),
+                    (block, delta) => {
+                        return Err(Error::Stream(format!(
+                            "unexpected delta {:?} for content block {:?}",
+                            delta, block
+                        )))
+                    }
+                }
+            }
+            Event::ContentBlockStop { index } => {
+                let index = index as usize;
+                let partial_json = self.partial_json.remove(&index);
+                let block = content_block_mut(&mut self.snapshot, index)?;
+
+                if let ContentPart::ToolUse {
+                    id, name, input, ..
+                } = block
+                {
+                    if let Some(partial_json) =
+                        partial_json.filter(|json| !json.as_str().is_empty())
+                    {
+                        *input = serde_json::from_str(partial_json.as_str())?;
+                    }
+
+                    let event = StreamEvent::ToolInput {
+                        index,
+                        id: id.to_owned(),
+                        name: name.to_owned(),
+                        input: input.to_owned(),
+                    };
+                    self.pending.push_back(event);
+                }
+
+                let content_block = block.to_owned();
+                self.pending.push_back(StreamEvent::ContentBlockStop {
+                    index,
+                    content_block,
+                });
+            }
+            Event::MessageDelta { delta, usage } => {
+                let snapshot = snapshot_mut(&mut self.snapshot)?;
+                snapshot.stop_reason = Some(delta.stop_reason);
+                snapshot.stop_sequence = delta.stop_sequence;
+                snapshot.usage.output_tokens = usage.output_tokens;
+                if usage.input_tokens.is_some() {
+                    snapshot.usage.input_tokens = usage.input_tokens;
+                }
+                if usage.cache_creation_input_tokens.is_some() {
+                    snapshot.usage.cache_creation_input_tokens = usage.cache_creation_input_tokens;
+                }
+                if usage.cache_read_input_tokens.is_some() {
+                    snapshot.usage.cache_read_input_tokens = usage.cache_read_input_tokens;
+                }
+            }
+            Event::MessageStop => self.pending.push_back(StreamEvent::MessageStop),
+            Event::Error { error } => return Err(ApiError::from(error).into()),
+        }
+
+        Ok(())
+    }
+}
+
+pub fn text_stream(mut events: EventStream) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
+    stream! {
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(Event::ContentBlockDelta {
+                    delta: ContentPart::TextDelta { text },
+                    ..
+                }) => yield Ok(text),
+                Ok(Event::MessageStop) => break,
+                Ok(Event::Error { error }) => {
+                    yield Err(ApiError::from(error).into());
+                    break;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    yield Err(err);
+                    break;
+                }
+            }
+        }
+    }
+    .boxed()
+}
+
+pub fn text_reader(events: EventStream) -> impl AsyncRead + Send + Unpin {
+    text_stream(events)
+        .map_err(std::io::Error::other)
+        .into_async_read()
+}
+
+pub fn parse_partial_json(json: &str) -> Option<Value> {
+    let mut partial = PartialJson::default();
+    partial.push(json);
+    partial.value()
+}
+
+/// Parses streamed JSON as it arrives. Each delta is scanned once and folded
+/// into a partially built document, so `value` only clones what has been built
+/// so far, closing any open strings, arrays and objects. Partial keys and
+/// incomplete literals or escapes are left out until they complete.
+#[derive(Debug, Default)]
+pub struct PartialJson {
+    buffer: String,
+    state: State,
+    stack: Vec<Container>,
+    token: Option<Token>,
+    root: Option<Value>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum State {
+    #[default]
+    Value,
+    Key,
+    Colon,
+    Comma,
+    End,
+    Failed,
+}
+
+#[derive(Debug)]
+enum Container {
+    // The key is set once it has been read and taken when its value completes.
+    Object(Map<String, Value>, Option<String>),
+    Array(Vec<Value>),
+}
+
+#[derive(Debug)]
+enum Token {
+    String {
+        key: bool,
+        text: String,
+        escape: Escape,
+        high_surrogate: Option<u32>,
+    },
+    Literal(String),
+}
+
+#[derive(Debug)]
+enum Escape {
+    None,
+    Backslash,
+    Unicode(String),
+}
+
+impl PartialJson {
+    pub fn push(&mut self, json: &str) {
+        self.buffer.push_str(json);
+
+        for c in json.chars() {
+            if self.state == State::Failed {
+                break;
+            }
+            if self.scan(c).is_none() {
+                self.state = State::Failed;
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn value(&self) -> Option<Value> {
+        if self.state == State::Failed {
+            return None;
+        }
+        if let Some(root) = &self.root {
+            return Some(root.to_owned());
+        }
+
+        let mut value = match &self.token {
+            Some(Token::String {
+                key: false, text, ..
+            }) => Some(Value::String(text.to_owned())),
+            Some(Token::Literal(literal)) => serde_json::from_str(literal).ok(),
+            _ => None,
+        };
+
+        for container in self.stack.iter().rev() {
+            value = Some(match container {
+                Container::Object(map, key) => {
+                    let mut map = map.to_owned();
+                    if let (Some(key), Some(value)) = (key, value) {
+                        map.insert(key.to_owned(), value);
+                    }
+                    Value::Object(map)
+                }
+                Container::Array(values) => {
+                    let mut values = values.to_owned();
+                    values.extend(value);
+                    Value::Array(values)
+                }
+            });
+        }
+
+        value
+    }
+
+    fn scan(&mut self, c: char) -> Option<()> {
+        match self.token.take() {
+            Some(Token::String {
+                key,
+                mut text,
+                escape,
+                mut high_surrogate,
+            }) => {
+                let escape = match escape {
+                    Escape::None => match c {
+                        '"' if high_surrogate.is_none() => {
+                            return if key {
+                                self.set_key(text)
+                            } else {
+                                self.emit(Value::String(text))
+                            };
+                        }
+                        '\\' => Escape::Backslash,
+                        _ if high_surrogate.is_some() => return None,
+                        c => {
+                            text.push(c);
+                            Escape::None
+                        }
+                    },
+                    Escape::Backslash => {
+                        let unescaped = match c {
+                            'u' => {
+                                return self.resume(
+                                    key,
+                                    text,
+                                    Escape::Unicode(String::new()),
+                                    high_surrogate,
+                                )
+                            }
+                            _ if high_surrogate.is_some() => return None,
+                            '"' | '\\' | '/' => c,
+                            'b' => '\u{8}',
+                            'f' => '\u{c}',
+                            'n' => '\n',
+                            'r' => '\r',
+                            't' => '\t',
+                            _ => return None,
+                        };
+                        text.push(unescaped);
+                        Escape::None
+                    }
+                    Escape::Unicode(mut hex) => {
+                        if !c.is_ascii_hexdigit() {
+                            return None;
+                        }
+                        hex.push(c);
+                        if hex.len() < 4 {
+                            Escape::Unicode(hex)
+                        } else {
+                            let code = u32::from_str_radix(&hex, 16).ok()?;
+                            match (high_surrogate.take(), code) {
+                                (None, 0xD800..=0xDBFF) => high_surrogate = Some(code),
+                                (Some(high), 0xDC00..=0xDFFF) => text.push(char::from_u32(
+                                    0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00),
+                                )?),
+                                (None, code) => text.push(char::from_u32(code)?),
+                                (Some(_), _) => return None,
+                            }
+                            Escape::None
+                        }
+                    }
+                };
+                return self.resume(key, text, escape, high_surrogate);
+            }
+            Some(Token::Literal(mut literal)) => {
+                if matches!(c, ',' | ':' | '}' | ']') || c.is_whitespace() {
+                    self.emit(serde_json::from_str(&literal).ok()?)?;
+                } else {
+                    literal.push(c);
+                    self.token = Some(Token::Literal(literal));
+                    return Some(());
+                }
+            }
+            None => {}
+        }
+
+        if c.is_whitespace() {
+            return Some(());
+        }
+
+        match (self.state, c) {
+            (State::Value, '{') => {
+                self.stack.push(Container::Object(Map::new(), None));
+                self.state = State::Key;
+            }
+            (State::Value, '[') => self.stack.push(Container::Array(Vec::new())),
+            (State::Value, '"') => return self.resume(false, String::new(), Escape::None, None),
+            (State::Value, '-' | '0'..='9' | 't' | 'f' | 'n') => {
+                self.token = Some(Token::Literal(c.to_string()))
+            }
+            (State::Key, '"') => return self.resume(true, String::new(), Escape::None, None),
+            (State::Colon, ':') => self.state = State::Value,
+            (State::Comma, ',') => {
+                self.state = match self.stack.last()? {
+                    Container::Object(..) => State::Key,
+                    Container::Array(_) => State::Value,
+                }
+            }
+            (State::Key | State::Comma, '}') | (State::Value | State::Comma, ']') => {
+                return self.close(c)
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    fn resume(
+        &mut self,
+        key: bool,
+        text: String,
+        escape: Escape,
+        high_surrogate: Option<u32>,
+    ) -> Option<()> {
+        self.token = Some(Token::String {
+            key,
+            text,
+            escape,
+            high_surrogate,
+        });
+        Some(())
+    }
+
+    fn set_key(&mut self, text: String) -> Option<()> {
+        match self.stack.last_mut()? {
+            Container::Object(_, key @ None) => *key = Some(text),
+            _ => return None,
+        }
+        self.state = State::Colon;
+        Some(())
+    }
+
+    fn close(&mut self, closer: char) -> Option<()> {
+        let value = match (self.stack.pop()?, closer) {
+            (Container::Object(map, None), '}') => Value::Object(map),
+            (Container::Array(values), ']') => Value::Array(values),
+            _ => return None,
+        };
+        self.emit(value)
+    }
+
+    fn emit(&mut self, value: Value) -> Option<()> {
+        match self.stack.last_mut() {
+            None => {
+                self.root = Some(value);
+                self.state = State::End;
+            }
+            Some(Container::Object(map, key)) => {
+                map.insert(key.take()?, value);
+                self.state = State::Comma;
+            }
+            Some(Container::Array(values)) => {
+                values.push(value);
+                self.state = State::Comma;
+            }
+        }
+        Some(())
+    }
+}
+
+fn snapshot_mut(snapshot: &mut Option<MessageResponse>) -> Result<&mut MessageResponse> {
+    snapshot
+        .as_mut()
+        .ok_or_else(|| Error::Stream("received an event before message_start".into()))
+}
+
+fn content_block_mut(
+    snapshot: &mut Option<MessageResponse>,
+    index: usize,
+) -> Result<&mut ContentPart> {
+    snapshot_mut(snapshot)?
+        .content
+        .get_mut(index)
+        .ok_or_else(|| Error::Stream(format!("unknown content block index {}", index)))
+}
+
+impl From<EventStream> for MessageStream {
+    fn from(events: EventStream) -> Self {
+        Self::new(events)
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            match this.events.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    if let Err(err) = this.apply(event) {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::ApiErrorKind,
+        messages::{ErrorDetails, StopReason},
+    };
+
+    use super::*;
+
+    fn events(data: &[&str]) -> EventStream {
+        let events = data
+            .iter()
+            .map(|data| serde_json::from_str::<Event>(data).map_err(Error::from))
+            .collect::<Vec<_>>();
+
+        futures::stream::iter(events).boxed()
+    }
+
+    const TOOL_USE_EVENTS: &[&str] = &[
+        r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-haiku-20240307","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"ping"}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Let me "}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"check."}}"#,
+        r#"{"type":"content_block_stop","index":0}"#,
+        r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_01","name":"get_weather","input":{}}}"#,
+        r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"location\": \"Par"}}"#,
+        r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"is\"}"}}"#,
+        r#"{"type":"content_block_stop","index":1}"#,
+        r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":42}}"#,
+        r#"{"type":"message_stop"}"#,
+    ];
+
+    #[tokio::test]
+    async fn test_message_stream_events() -> Result<()> {
+        let events = MessageStream::new(events(TOOL_USE_EVENTS))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        assert!(matches!(&events[0], StreamEvent::Text { text, .. } if text == "Let me "));
+        assert!(matches!(
+            &events[2],
+            StreamEvent::ContentBlockStop { index: 0, content_block: ContentPart::Text { text, .. } }
+                if text == "Let me check."
+        ));
+        assert!(matches!(
+            &events[5],
+            StreamEvent::ToolInput { name, input, .. }
+                if name == "get_weather" && input["location"] == "Paris"
+        ));
+        assert!(matches!(events.last(), Some(StreamEvent::MessageStop)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_text_stream() -> Result<()> {
+        let text = text_stream(events(TOOL_USE_EVENTS))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(text, vec!["Let me ", "check."]);
+
+        let mut reader = text_reader(events(TOOL_USE_EVENTS));
+        let mut text = String::new();
+        futures::AsyncReadExt::read_to_string(&mut reader, &mut text)
+            .await
+            .map_err(Error::transport)?;
+        assert_eq!(text, "Let me check.");
+
+        let mut stream = text_stream(
+            events(&TOOL_USE_EVENTS[..4])
+                .chain(futures::stream::iter([
+                    Ok(Event::Error {
+                        error: ErrorDetails {
+                            kind: ApiErrorKind::Overloaded,
+                            message: "Overloaded".into(),
+                        },
+                    }),
+                    Ok(Event::MessageStop),
+                ]))
+                .boxed(),
+        );
+        assert_eq!(stream.next().await.transpose()?.as_deref(), Some("Let me "));
+        assert!(matches!(stream.next().await, Some(Err(Error::Api(_)))));
+        assert!(stream.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_partial_json() {
+        let cases = [
+            ("", None),
+            ("{", Some(serde_json::json!({}))),
+            (
+                r#"{"path": "src/ma"#,
+                Some(serde_json::json!({ "path": "src/ma" })),
+            ),
+            (
+                r#"{"path": "a.rs", "lines": [1, 2"#,
+                Some(serde_json::json!({ "path": "a.rs", "lines": [1, 2] })),
+            ),
+            (r#"{"a": 1, "ke"#, Some(serde_json::json!({ "a": 1 }))),
+            (r#"{"a": tr"#, Some(serde_json::json!({}))),
+            (r#"{"a": "x\"#, Some(serde_json::json!({ "a": "x" }))),
+            (r#"{"a": "\u00e"#, Some(serde_json::json!({ "a": "" }))),
+            (r#"{"a": 1.5}"#, Some(serde_json::json!({ "a": 1.5 }))),
+            (
+                r#"{"a": {"b": [true, "x"#,
+                Some(serde_json::json!({ "a": { "b": [true, "x"] } })),
+            ),
+            (r#"["{", "]"#, Some(serde_json::json!(["{", "]"]))),
+            (r#"{"e": "\ud83d"#, Some(serde_json::json!({ "e": "" }))),
+            (
+                r#"{"e": "\ud83d\ude00"}"#,
+                Some(serde_json::json!({ "e": "\u{1f600}" })),
+            ),
+            (r#"{"a": 1 "b"#, None),
+        ];
+
+        for (json, expected) in cases {
+            assert_eq!(parse_partial_json(json), expected, "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_partial_json_incremental() {
+        let json = r#"{"path": "src/main.rs", "lines": [10, 20], "note": "caf\u00e9"}"#;
+        let mut partial = PartialJson::default();
+        let mut last = None;
+
+        for (index, c) in json.char_indices() {
+            partial.push(&json[index..index + c.len_utf8()]);
+            let value = partial.value();
+            assert_eq!(value, parse_partial_json(&json[..index + c.len_utf8()]));
+            if value.is_some() {
+                last = value;
+            }
+        }
+
+        assert_eq!(last, serde_json::from_str(json).ok());
+        assert_eq!(partial.as_str(), json);
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_partial_tool_input() -> Result<()> {
+        let mut stream = MessageStream::new(events(TOOL_USE_EVENTS));
+        let mut inputs = Vec::new();
+
+        while let Some(event) = stream.next().await {
+            if let StreamEvent::PartialToolInput { input, .. } = event? {
+                inputs.push(input);
+            }
+        }
+
+        assert_eq!(
+            inputs,
+            vec![
+                serde_json::json!({ "location": "Par" }),
+                serde_json::json!({ "location": "Paris" }),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_thinking_signature() -> Result<()> {
+        let message = MessageStream::new(events(&[
+            r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-3-7-sonnet-20250219","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me think."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":12}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]))
+        .final_message()
+        .await?;
+
+        match &message.content[0] {
+            ContentPart::Thinking {
+                thinking,
+                signature,
+            } => {
+                assert_eq!(thinking, "Let me think.");
+                assert_eq!(signature, "sig");
+            }
+            part => panic!("unexpected content part: {:?}", part),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_stream_final_message() -> Result<()> {
+        let message = MessageStream::new(events(TOOL_USE_EVENTS))
+            .final_message()
+            .await?;
+
+        assert_eq!(message.id, "msg_01");
+        assert_eq!(message.content.len(), 2);
+        assert!(matches!(message.stop_reason, Some(StopReason::ToolUse)));
+        assert_eq!(message.usage.input_tokens, Some(25));
+        assert_eq!(message.usage.output_tokens, 42);
+        match &message.content[1] {
+            ContentPart::ToolUse { input, .. } => {
+                assert_eq!(input, &serde_json::json!({ "location": "Paris" }))
+            }
+            part => panic!("unexpected content part: {:?}", part),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    messages::{Content, ContentPart, CreateMessageRequest, Message, Messages, ToolChoiceKind},
+    tools::tool_for,
+};
+
+const STRUCTURED_OUTPUT_TOOL: &str = "structured_output";
+
+#[async_trait]
+pub trait MessagesStructured: Messages {
+    async fn messages_structured<T>(&self, request: CreateMessageRequest) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned + Send,
+    {
+        self.messages_structured_with_retries(request, 0).await
+    }
+
+    async fn messages_structured_with_retries<T>(
+        &self,
+        mut request: CreateMessageRequest,
+        max_retries: u32,
+    ) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned + Send,
+    {
+        let tool = tool_for::<T>(STRUCTURED_OUTPUT_TOOL)?;
+        let validator =
+            jsonschema::validator_for(&Value::Object(tool.input_schema.as_map().clone()))
+                .map_err(|err| Error::validation(format!("invalid output schema: {}", err)))?;
+
+        request.tools = Some(vec![tool]);
+        request.tool_choice = Some(
+            ToolChoiceKind::Tool {
+                name: STRUCTURED_OUTPUT_TOOL.into(),
+            }
+            .into(),
+        );
+
+        let mut attempt = 0;
+        loop {
+            let response = self.messages(request.clone()).await?;
+            let (id, input) = response
+                .content
+                .iter()
+                .find_map(|part| match part {
+                    ContentPart::ToolUse {
+                        id, name, input, ..
+                    } if name == STRUCTURED_OUTPUT_TOOL => Some((id.to_owned(), input.to_owned())),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    Error::validation("response did not include the structured output tool_use")
+                })?;
+
+            let errors = validator
+                .iter_errors(&input)
+                .map(|err| format!("{} at {}", err, err.instance_path))
+                .collect::<Vec<_>>();
+            let result = match errors.is_empty() {
+                true => serde_json::from_value::<T>(input).map_err(|source| Error::ToolInput {
+                    name: STRUCTURED_OUTPUT_TOOL.into(),
+                    source,
+                }),
+                false => Err(Error::validation(format!(
+                    "structured output does not match the schema: {}",
+                    errors.join("; ")
+                ))),
+            };
+
+            match result {
+                Err(err) if attempt < max_retries => {
+                    attempt += 1;
+                    request
+                        .messages
+                        .push(Message::assistant(Content::Multi(response.content)));
+                    request.messages.push(Message::user(Content::Multi(vec![
+                        ContentPart::tool_error(id, err.to_string().into()),
+                    ])));
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<C> MessagesStructured for C where C: Messages + ?Sized {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        messages::StopReason,
+        test_util::{request, response, tool_use, ScriptedClient},
+    };
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(Debug, serde::Deserialize, JsonSchema)]
+    struct Sentiment {
+        label: String,
+        score: f32,
+    }
+
+    fn client(inputs: Vec<Value>) -> ScriptedClient {
+        ScriptedClient::new(
+            inputs
+                .into_iter()
+                .map(|input| {
+                    response(
+                        vec![tool_use("toolu_01", STRUCTURED_OUTPUT_TOOL, input)],
+                        StopReason::ToolUse,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_messages_structured() -> Result<()> {
+        let client = client(vec![
+            serde_json::json!({ "label": "positive", "score": 0.9 }),
+        ]);
+
+        let sentiment = client
+            .messages_structured::<Sentiment>(request("I love this!")?)
+            .await?;
+
+        assert_eq!(sentiment.label, "positive");
+        let requests = client.requests();
+        assert!(matches!(
+            requests[0].tool_choice.as_ref().map(|choice| &choice.kind),
+            Some(ToolChoiceKind::Tool { name }) if name == STRUCTURED_OUTPUT_TOOL
+        ));
+        assert_eq!(
+            serde_json::to_value(&requests[0])?["tool_choice"],
+            serde_json::json!({ "type": "tool", "name": STRUCTURED_OUTPUT_TOOL })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_messages_structured_retries() -> Result<()> {
+        let client = client(vec![
+            serde_json::json!({ "label": "positive" }),
+            serde_json::json!({ "label": "positive", "score": 0.9 }),
+        ]);
+
+        let sentiment = client
+            .messages_structured_with_retries::<Sentiment>(request("I love this!")?, 1)
+            .await?;
+
+        assert_eq!(sentiment.score, 0.9);
+        assert_eq!(client.requests()[1].messages.len(), 3);
+
+        let client = self::client(vec![serde_json::json!({ "label": 1 })]);
+        let err = client
+            .messages_structured::<Sentiment>(request("I love this!")?)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+
+        Ok(())
+    }
+}
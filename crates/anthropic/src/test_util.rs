@@ -0,0 +1,92 @@
+use std::{pin::Pin, sync::Mutex};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    messages::{
+        ContentPart, CreateMessageRequest, Event, Message, MessageResponse, Messages,
+        MessagesStream, StopReason, Usage,
+    },
+};
+
+/// A [`Messages`] client that replays queued responses and records every request.
+pub(crate) struct ScriptedClient {
+    responses: Mutex<Vec<MessageResponse>>,
+    requests: Mutex<Vec<CreateMessageRequest>>,
+}
+
+impl ScriptedClient {
+    pub(crate) fn new(responses: Vec<MessageResponse>) -> Self {
+        Self {
+            responses: Mutex::new(responses),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    pub(crate) fn requests(&self) -> Vec<CreateMessageRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl Messages for ScriptedClient {
+    async fn messages(&self, request: CreateMessageRequest) -> Result<MessageResponse> {
+        self.requests.lock().unwrap().push(request);
+
+        let mut responses = self.responses.lock().unwrap();
+        match responses.is_empty() {
+            true => Err(Error::validation("no scripted response left")),
+            false => Ok(responses.remove(0)),
+        }
+    }
+}
+
+#[async_trait]
+impl MessagesStream for ScriptedClient {
+    async fn messages_stream(
+        &self,
+        _: CreateMessageRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>> {
+        Err(Error::Unsupported("streaming".into()))
+    }
+}
+
+pub(crate) fn request(text: &str) -> Result<CreateMessageRequest> {
+    CreateMessageRequest::builder()
+        .model("claude-3-haiku-20240307")
+        .messages(vec![Message::user(text.into())])
+        .max_tokens(1024)
+        .build()
+}
+
+pub(crate) fn response(content: Vec<ContentPart>, stop_reason: StopReason) -> MessageResponse {
+    MessageResponse {
+        id: "msg_01".into(),
+        model: "claude-3-haiku-20240307".into(),
+        role: "assistant".into(),
+        content,
+        stop_reason: Some(stop_reason),
+        stop_sequence: None,
+        usage: Usage {
+            input_tokens: Some(10),
+            output_tokens: 5,
+            ..Default::default()
+        },
+    }
+}
+
+pub(crate) fn tool_use(id: &str, name: &str, input: Value) -> ContentPart {
+    ContentPart::ToolUse {
+        id: id.into(),
+        name: name.into(),
+        input,
+        cache_control: None,
+    }
+}
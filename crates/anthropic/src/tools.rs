@@ -0,0 +1,367 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use futures::future::{join_all, BoxFuture, FutureExt};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    messages::{
+        AnthropicSdk, Content, ContentPart, CreateMessageRequest, Message, MessageResponse,
+        StopReason, Tool, ToolInputSchema, Usage,
+    },
+};
+
+const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+type ToolHandler = Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Content>> + Send + Sync>;
+
+pub trait TypedTool: JsonSchema + DeserializeOwned {
+    const NAME: &'static str;
+
+    fn tool() -> Result<Tool> {
+        tool_for::<Self>(Self::NAME)
+    }
+
+    fn from_input(input: &Value) -> Result<Self> {
+        serde_json::from_value(input.to_owned()).map_err(|source| Error::ToolInput {
+            name: Self::NAME.to_string(),
+            source,
+        })
+    }
+
+    fn from_tool_use(part: &ContentPart) -> Result<Self> {
+        match part {
+            ContentPart::ToolUse { name, input, .. } if name == Self::NAME => {
+                Self::from_input(input)
+            }
+            ContentPart::ToolUse { name, .. } => Err(Error::validation(format!(
+                "expected a tool_use for {}, got {}",
+                Self::NAME,
+                name
+            ))),
+            _ => Err(Error::validation("expected a tool_use content block")),
+        }
+    }
+}
+
+pub(crate) fn tool_for<T>(name: &str) -> Result<Tool>
+where
+    T: JsonSchema,
+{
+    let mut schema = Value::from(schemars::schema_for!(T));
+    let description = match schema.as_object_mut() {
+        Some(schema) => {
+            schema.remove("$schema");
+            schema.remove("title");
+            schema
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        }
+        None => None,
+    };
+
+    Ok(Tool {
+        description,
+        name: name.to_string(),
+        input_schema: ToolInputSchema::try_from(schema)?,
+        cache_control: None,
+    })
+}
+
+#[derive(Debug)]
+pub struct ToolRun {
+    pub response: MessageResponse,
+    pub messages: Vec<Message>,
+    pub usage: Usage,
+    /// The run stopped at `max_iterations` with the last `tool_use` blocks
+    /// unanswered, so `messages` can't be sent back to the API as-is.
+    pub max_iterations_reached: bool,
+}
+
+pub struct ToolRunner {
+    tools: HashMap<String, (Tool, ToolHandler)>,
+    max_iterations: usize,
+}
+
+impl Default for ToolRunner {
+    fn default() -> Self {
+        Self {
+            tools: HashMap::new(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+}
+
+impl ToolRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tool<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Content>> + Send + 'static,
+    {
+        let handler: ToolHandler = Arc::new(move |input| handler(input).boxed());
+        self.tools.insert(tool.name.to_owned(), (tool, handler));
+        self
+    }
+
+    pub fn typed_tool<T, F, Fut>(self, handler: F) -> Result<Self>
+    where
+        T: TypedTool + Send + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Content>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        Ok(self.tool(T::tool()?, move |input| {
+            let handler = handler.clone();
+            async move { handler(T::from_input(&input)?).await }
+        }))
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub async fn run<C>(&self, client: &C, mut request: CreateMessageRequest) -> Result<ToolRun>
+    where
+        C: AnthropicSdk + ?Sized,
+    {
+        if !self.tools.is_empty() {
+            let tools = request.tools.get_or_insert_with(Vec::new);
+            for (tool, _) in self.tools.values() {
+                if !tools.iter().any(|existing| existing.name == tool.name) {
+                    tools.push(tool.to_owned());
+                }
+            }
+        }
+
+        let mut usage = Usage::default();
+        let mut iteration = 0;
+
+        loop {
+            iteration += 1;
+
+            let response = client.messages(request.clone()).await?;
+            usage += &response.usage;
+            request
+                .messages
+                .push(Message::assistant(Content::Multi(response.content.clone())));
+
+            let calls = response
+                .content
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::ToolUse {
+                        id, name, input, ..
+                    } => Some(self.call(id.to_owned(), name, input.to_owned())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            let pending =
+                matches!(response.stop_reason, Some(StopReason::ToolUse)) && !calls.is_empty();
+            if !pending || iteration >= self.max_iterations {
+                drop(calls);
+
+                return Ok(ToolRun {
+                    response,
+                    messages: request.messages,
+                    usage,
+                    max_iterations_reached: pending,
+                });
+            }
+
+            let results = join_all(calls).await;
+
+            request
+                .messages
+                .push(Message::user(Content::Multi(results)));
+        }
+    }
+
+    async fn call(&self, tool_use_id: String, name: &str, input: Value) -> ContentPart {
+        let result = match self.tools.get(name) {
+            Some((_, handler)) => handler(input).await,
+            None => Err(Error::validation(format!("unknown tool {}", name))),
+        };
+
+        match result {
+            Ok(content) => ContentPart::tool_result(tool_use_id, content),
+            Err(err) => ContentPart::tool_error(tool_use_id, err.to_string().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{request, response, tool_use, ScriptedClient};
+
+    use super::*;
+
+    /// Get the current weather in a given location.
+    #[allow(dead_code)]
+    #[derive(Debug, serde::Deserialize, JsonSchema)]
+    struct GetWeather {
+        /// The city and state, e.g. San Francisco, CA
+        location: String,
+        unit: Option<Unit>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, serde::Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    impl TypedTool for GetWeather {
+        const NAME: &'static str = "get_weather";
+    }
+
+    #[test]
+    fn test_typed_tool_definition() -> Result<()> {
+        let tool = GetWeather::tool()?;
+
+        assert_eq!(tool.name, "get_weather");
+        assert_eq!(
+            tool.description.as_deref(),
+            Some("Get the current weather in a given location.")
+        );
+        assert_eq!(
+            tool.input_schema.get("required"),
+            Some(&serde_json::json!(["location"]))
+        );
+        assert!(tool.input_schema.get("$schema").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_tool_from_tool_use() -> Result<()> {
+        let input = GetWeather::from_tool_use(&tool_use(
+            "toolu_01",
+            "get_weather",
+            serde_json::json!({ "location": "Paris", "unit": "celsius" }),
+        ))?;
+        assert_eq!(input.location, "Paris");
+
+        let err = GetWeather::from_tool_use(&tool_use(
+            "toolu_01",
+            "get_weather",
+            serde_json::json!({ "unit": "kelvin" }),
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::ToolInput { ref name, .. } if name == "get_weather"));
+
+        let err =
+            GetWeather::from_tool_use(&tool_use("toolu_01", "get_time", serde_json::json!({})))
+                .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_runner() -> Result<()> {
+        let client = ScriptedClient::new(vec![
+            response(
+                vec![
+                    tool_use(
+                        "toolu_01",
+                        "get_weather",
+                        serde_json::json!({ "location": "Paris" }),
+                    ),
+                    tool_use("toolu_02", "get_weather", serde_json::json!({})),
+                ],
+                StopReason::ToolUse,
+            ),
+            response(vec!["Sunny in Paris.".into()], StopReason::EndTurn),
+        ]);
+
+        let runner = ToolRunner::new().typed_tool(|input: GetWeather| async move {
+            Ok(format!("Sunny in {}", input.location).into())
+        })?;
+        let run = runner
+            .run(&client, request("What's the weather in Paris?")?)
+            .await?;
+
+        assert!(matches!(
+            run.response.stop_reason,
+            Some(StopReason::EndTurn)
+        ));
+        assert_eq!(run.messages.len(), 4);
+        assert_eq!(run.usage.input_tokens, Some(20));
+        assert_eq!(run.usage.output_tokens, 10);
+        match &run.messages[2].content {
+            Content::Multi(results) => {
+                assert!(matches!(
+                    &results[0],
+                    ContentPart::ToolResult { tool_use_id, is_error: None, .. }
+                        if tool_use_id == "toolu_01"
+                ));
+                assert!(matches!(
+                    &results[1],
+                    ContentPart::ToolResult {
+                        is_error: Some(true),
+                        ..
+                    }
+                ));
+            }
+            content => panic!("unexpected content: {:?}", content),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_runner_max_iterations() -> Result<()> {
+        let tool_use = tool_use(
+            "toolu_01",
+            "get_weather",
+            serde_json::json!({ "location": "Paris" }),
+        );
+        let client = ScriptedClient::new(vec![
+            response(vec![tool_use.clone()], StopReason::ToolUse),
+            response(vec![tool_use], StopReason::ToolUse),
+        ]);
+
+        let runner = ToolRunner::new()
+            .typed_tool(|input: GetWeather| async move {
+                Ok(format!("Sunny in {}", input.location).into())
+            })?
+            .max_iterations(2);
+        let run = runner
+            .run(&client, request("What's the weather in Paris?")?)
+            .await?;
+        assert!(run.max_iterations_reached);
+        assert_eq!(run.messages.len(), 4);
+        assert_eq!(run.usage.input_tokens, Some(20));
+        assert_eq!(run.usage.output_tokens, 10);
+        assert_eq!(client.remaining(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_runner_tool_use_without_calls() -> Result<()> {
+        let client = ScriptedClient::new(vec![response(
+            vec!["Let me check.".into()],
+            StopReason::ToolUse,
+        )]);
+
+        let run = ToolRunner::new()
+            .run(&client, request("What's the weather in Paris?")?)
+            .await?;
+        assert!(!run.max_iterations_reached);
+        assert_eq!(run.messages.len(), 2);
+
+        Ok(())
+    }
+}
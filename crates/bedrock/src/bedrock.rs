@@ -1,48 +1,133 @@
-use std::{collections::HashSet, pin::Pin};
+use std::{collections::HashSet, pin::Pin, sync::Arc};
 
 pub use anthropic::messages;
-use anthropic::messages::{
-    Content, ContentPart, CreateMessageRequest, CreateMessageRequestWithStream,
-    CreateMessageResponse, Event, EventMessageDelta, ImageSource, MediaType, Message,
-    MessageResponse, MessageResponseStream, Messages, MessagesStream, Metadata, StopReason, Tool,
-    ToolChoice, Usage,
+use anthropic::{
+    messages::{
+        CacheControl, Content, ContentPart, CountTokens, CountTokensResponse, CreateMessageRequest,
+        DocumentMediaType, DocumentSource, Event, EventMessageDelta, ImageSource, MediaType,
+        Message, MessageResponse, MessageResponseStream, Messages, MessagesStream, StopReason,
+        ThinkingConfig, Tool, ToolChoice, ToolInputSchema, Usage,
+    },
+    response, ApiError, ApiErrorKind, Error, ModelCapabilities, Provider, Result,
 };
-use anyhow::{anyhow, Result};
 use async_stream::stream;
 use async_trait::async_trait;
 use aws_config::SdkConfig;
-use aws_sdk_bedrockruntime::types;
+use aws_sdk_bedrockruntime::{
+    error::{ProvideErrorMetadata, SdkError},
+    types,
+};
 use aws_types::request_id::RequestId;
-use futures::{Stream, StreamExt};
+use futures::{AsyncRead, AsyncReadExt, Stream, StreamExt};
+use http_client::{
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        Method, Request,
+    },
+    AsyncBody, HttpClient,
+};
 
-const DEFAULT_API_VERSION: &str = "bedrock-2023-05-31";
+// Bedrock rejects images larger than 3.75 MB.
+const MAX_IMAGE_BYTES: u64 = 3_750_000;
 
-pub enum Model {
-    ClaudeThreeDotFiveSonnet,
-    ClaudeThreeDotFiveSonnetV1,
-    ClaudeThreeSonnet,
-    ClaudeThreeOpus,
-    ClaudeThreeHaiku,
-}
+anthropic::__private::model_type!(Model);
 
-impl ToString for Model {
-    fn to_string(&self) -> String {
-        match self {
-            Model::ClaudeThreeDotFiveSonnet => {
-                "anthropic.claude-3-5-sonnet-20241022-v2:0".to_string()
-            }
-            Model::ClaudeThreeDotFiveSonnetV1 => {
-                "anthropic.claude-3-5-sonnet-20240620-v1:0".to_string()
-            }
-            Model::ClaudeThreeSonnet => "anthropic.claude-3-sonnet-20240229-v1:0".to_string(),
-            Model::ClaudeThreeOpus => "anthropic.claude-3-opus-20240229-v1:0".to_string(),
-            Model::ClaudeThreeHaiku => "anthropic.claude-3-haiku-20240307-v1:0".to_string(),
+impl Model {
+    pub const CLAUDE_OPUS_4_20250514: Model =
+        Model::from_static("anthropic.claude-opus-4-20250514-v1:0");
+    pub const CLAUDE_SONNET_4_20250514: Model =
+        Model::from_static("anthropic.claude-sonnet-4-20250514-v1:0");
+    pub const CLAUDE_3_7_SONNET_20250219: Model =
+        Model::from_static("anthropic.claude-3-7-sonnet-20250219-v1:0");
+    pub const CLAUDE_3_5_SONNET_20241022: Model =
+        Model::from_static("anthropic.claude-3-5-sonnet-20241022-v2:0");
+    pub const CLAUDE_3_5_SONNET_20240620: Model =
+        Model::from_static("anthropic.claude-3-5-sonnet-20240620-v1:0");
+    pub const CLAUDE_3_5_HAIKU_20241022: Model =
+        Model::from_static("anthropic.claude-3-5-haiku-20241022-v1:0");
+    pub const CLAUDE_3_OPUS_20240229: Model =
+        Model::from_static("anthropic.claude-3-opus-20240229-v1:0");
+    pub const CLAUDE_3_SONNET_20240229: Model =
+        Model::from_static("anthropic.claude-3-sonnet-20240229-v1:0");
+    pub const CLAUDE_3_HAIKU_20240307: Model =
+        Model::from_static("anthropic.claude-3-haiku-20240307-v1:0");
+
+    pub fn capabilities(&self) -> Option<&'static ModelCapabilities> {
+        self.to_anthropic().and_then(|model| model.capabilities())
+    }
+
+    pub fn from_anthropic(model: &anthropic::Model) -> Option<Self> {
+        model.provider_id(Provider::Bedrock).map(Model::from_static)
+    }
+
+    pub fn to_anthropic(&self) -> Option<anthropic::Model> {
+        anthropic::Model::from_provider_id(Provider::Bedrock, self.without_inference_profile().id())
+    }
+
+    pub fn inference_profile(&self) -> Option<&str> {
+        self.0
+            .split_once('.')
+            .filter(|(_, id)| id.starts_with("anthropic."))
+            .map(|(profile, _)| profile)
+    }
+
+    pub fn with_inference_profile<S>(&self, profile: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        Model::new(format!(
+            "{}.{}",
+            profile.as_ref(),
+            self.without_inference_profile()
+        ))
+    }
+
+    pub fn without_inference_profile(&self) -> Self {
+        match self.inference_profile() {
+            Some(profile) => Model::new(&self.0[profile.len() + 1..]),
+            None => self.clone(),
         }
     }
 }
 
+impl TryFrom<&anthropic::Model> for Model {
+    type Error = Error;
+
+    fn try_from(model: &anthropic::Model) -> Result<Self, Self::Error> {
+        Model::from_anthropic(model).ok_or_else(|| {
+            Error::validation(format!("no Amazon Bedrock model id known for {}", model))
+        })
+    }
+}
+
 pub struct AnthropicBedrock {
     client: aws_sdk_bedrockruntime::Client,
+    http_client: Option<Arc<dyn HttpClient>>,
+    inference_profile: Option<String>,
+}
+
+fn validate_request(request: &CreateMessageRequest) -> Result<()> {
+    let citations_enabled = request
+        .messages
+        .iter()
+        .any(|message| match &message.content {
+            Content::Multi(parts) => parts.iter().any(|part| match part {
+                ContentPart::Document { citations, .. }
+                | ContentPart::SearchResult { citations, .. } => citations
+                    .as_ref()
+                    .is_some_and(|citations| citations.enabled),
+                _ => false,
+            }),
+            Content::Single(_) => false,
+        });
+
+    if citations_enabled {
+        return Err(Error::Unsupported(
+            "citations are not available on Amazon Bedrock".into(),
+        ));
+    }
+
+    Ok(())
 }
 
 fn filter_content_blocks(content: Vec<ContentPart>) -> Vec<ContentPart> {
@@ -63,76 +148,257 @@ impl AnthropicBedrock {
     pub fn new(config: &SdkConfig) -> Self {
         Self {
             client: aws_sdk_bedrockruntime::Client::new(config),
+            http_client: None,
+            inference_profile: None,
+        }
+    }
+
+    pub fn with_http_client(mut self, http_client: Arc<dyn HttpClient>) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    pub fn with_inference_profile<S>(mut self, profile: S) -> Self
+    where
+        S: ToString,
+    {
+        self.inference_profile = Some(profile.to_string());
+        self
+    }
+
+    async fn inline_images(&self, messages: &[Message]) -> Result<Vec<Message>> {
+        let mut inlined = Vec::with_capacity(messages.len());
+
+        for message in messages {
+            let content = match &message.content {
+                Content::Multi(parts) => {
+                    let mut resolved = Vec::with_capacity(parts.len());
+                    for part in parts {
+                        resolved.push(match part {
+                            ContentPart::ToolResult {
+                                tool_use_id,
+                                content: Content::Multi(content),
+                                is_error,
+                                cache_control,
+                            } => {
+                                let mut inner = Vec::with_capacity(content.len());
+                                for part in content {
+                                    inner.push(self.inline_image(part).await?);
+                                }
+                                ContentPart::ToolResult {
+                                    tool_use_id: tool_use_id.to_owned(),
+                                    content: Content::Multi(inner),
+                                    is_error: *is_error,
+                                    cache_control: cache_control.to_owned(),
+                                }
+                            }
+                            part => self.inline_image(part).await?,
+                        });
+                    }
+                    Content::Multi(resolved)
+                }
+                content => content.to_owned(),
+            };
+
+            inlined.push(Message {
+                role: message.role.to_owned(),
+                content,
+            });
+        }
+
+        Ok(inlined)
+    }
+
+    async fn inline_image(&self, part: &ContentPart) -> Result<ContentPart> {
+        match part {
+            ContentPart::Image {
+                source: ImageSource::Url { url },
+                cache_control,
+            } => Ok(ContentPart::Image {
+                source: self.fetch_image(url).await?,
+                cache_control: cache_control.to_owned(),
+            }),
+            ContentPart::Image {
+                source: ImageSource::File { .. },
+                ..
+            } => Err(Error::Unsupported(
+                "file image sources are not available on Amazon Bedrock".into(),
+            )),
+            part => Ok(part.to_owned()),
+        }
+    }
+
+    async fn fetch_image(&self, url: &str) -> Result<ImageSource> {
+        let http_client = self.http_client.as_ref().ok_or_else(|| {
+            Error::validation("an http client is required to fetch image URLs on Amazon Bedrock")
+        })?;
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(url)
+            .body(AsyncBody::default())
+            .map_err(Error::validation)?;
+        let response = http_client.send(request).await.map_err(Error::transport)?;
+        let mut response = response::error_for_status(response).await?;
+
+        let media_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(MediaType::from_mime)
+            .ok_or_else(|| {
+                Error::validation(format!("unsupported image content type for {}", url))
+            })?;
+
+        let content_length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let bytes = read_image(response.body_mut(), content_length, url).await?;
+
+        Ok(ImageSource::Base64 {
+            media_type,
+            data: aws_smithy_types::base64::encode(bytes),
+        })
+    }
+
+    fn model_id(&self, model: &str) -> String {
+        let model = Model::from_anthropic(&anthropic::Model::from(model))
+            .unwrap_or_else(|| Model::from(model));
+
+        match &self.inference_profile {
+            Some(profile)
+                if model.inference_profile().is_none() && model.id().starts_with("anthropic.") =>
+            {
+                model.with_inference_profile(profile).to_string()
+            }
+            _ => model.to_string(),
         }
     }
 }
 
-#[derive(Debug, serde::Serialize)]
-struct BedrockCreateMessageRequest {
-    messages: Vec<Message>,
-    max_tokens: u32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    metadata: Option<Metadata>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    stop_sequences: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<Content>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tool_choice: Option<ToolChoice>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tools: Option<Vec<Tool>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    top_k: Option<u32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    top_p: Option<f32>,
-    anthropic_version: String,
-}
-
-impl From<CreateMessageRequestWithStream> for BedrockCreateMessageRequest {
-    fn from(value: CreateMessageRequestWithStream) -> Self {
-        Self {
-            messages: value.create_message_request.messages,
-            max_tokens: value.create_message_request.max_tokens,
-            metadata: value.create_message_request.metadata,
-            stop_sequences: value.create_message_request.stop_sequences,
-            system: value.create_message_request.system,
-            temperature: value.create_message_request.temperature,
-            tool_choice: value.create_message_request.tool_choice,
-            tools: value.create_message_request.tools,
-            top_k: value.create_message_request.top_k,
-            top_p: value.create_message_request.top_p,
-            anthropic_version: DEFAULT_API_VERSION.into(),
+async fn read_image(
+    body: impl AsyncRead + Unpin,
+    content_length: Option<u64>,
+    url: &str,
+) -> Result<Vec<u8>> {
+    let too_large = || {
+        Error::validation(format!(
+            "image at {} exceeds the {} byte limit on Amazon Bedrock",
+            url, MAX_IMAGE_BYTES
+        ))
+    };
+
+    if content_length.is_some_and(|length| length > MAX_IMAGE_BYTES) {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    body.take(MAX_IMAGE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(Error::transport)?;
+
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+        return Err(too_large());
+    }
+
+    Ok(bytes)
+}
+
+fn map_sdk_error<E, R>(err: SdkError<E, R>) -> Error
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    match err {
+        SdkError::ServiceError(context) => {
+            let err = context.into_err();
+            let (kind, status) = match err.code() {
+                Some("ValidationException") => (ApiErrorKind::InvalidRequest, Some(400)),
+                Some("AccessDeniedException") => (ApiErrorKind::Permission, Some(403)),
+                Some("ResourceNotFoundException") => (ApiErrorKind::NotFound, Some(404)),
+                Some("ModelTimeoutException") => (ApiErrorKind::Api, Some(408)),
+                Some("ThrottlingException") => (ApiErrorKind::RateLimit, Some(429)),
+                Some("ModelNotReadyException") => (ApiErrorKind::Overloaded, Some(429)),
+                Some("InternalServerException") => (ApiErrorKind::Api, Some(500)),
+                Some("ServiceUnavailableException") => (ApiErrorKind::Overloaded, Some(503)),
+                Some(code) => (ApiErrorKind::Other(code.to_string()), None),
+                None => (ApiErrorKind::Api, None),
+            };
+
+            Error::Api(ApiError {
+                status,
+                kind,
+                message: err
+                    .message()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| err.to_string()),
+                request_id: err.meta().request_id().map(ToString::to_string),
+            })
         }
+        // The SDK couldn't even build the request, so retrying won't help.
+        err @ SdkError::ConstructionFailure(_) => Error::validation(err),
+        err => Error::transport(err),
     }
 }
 
 fn attach_tools(
     tool_config: types::builders::ToolConfigurationBuilder,
     tools: Vec<Tool>,
-) -> types::builders::ToolConfigurationBuilder {
-    tool_config.set_tools(Some(
-        tools
-            .iter()
-            .map(|tool| {
-                types::Tool::ToolSpec(
-                    types::ToolSpecification::builder()
-                        .name(tool.name.clone())
-                        .set_description(tool.description.clone())
-                        .input_schema(types::ToolInputSchema::Json(
-                            serde_json::to_value(&tool.input_schema)
-                                .and_then(|val| {
-                                    serde_json::from_value::<aws_smithy_types::Document>(val)
-                                })
-                                .unwrap(),
-                        ))
-                        .build()
-                        .unwrap(),
-                )
-            })
-            .collect(),
-    ))
+) -> Result<types::builders::ToolConfigurationBuilder> {
+    let mut specs = Vec::with_capacity(tools.len());
+    for tool in tools {
+        specs.push(types::Tool::ToolSpec(
+            types::ToolSpecification::builder()
+                .name(tool.name)
+                .set_description(tool.description)
+                .input_schema(types::ToolInputSchema::Json(tool_input_schema(
+                    &tool.input_schema,
+                )?))
+                .build()
+                .map_err(Error::validation)?,
+        ));
+        specs.extend(cache_point(tool.cache_control.as_ref()).map(types::Tool::CachePoint));
+    }
+
+    Ok(tool_config.set_tools(Some(specs)))
+}
+
+fn tool_input_schema(schema: &ToolInputSchema) -> Result<aws_smithy_types::Document> {
+    serde_json::to_value(schema)
+        .and_then(serde_json::from_value)
+        .map_err(Error::validation)
+}
+
+fn additional_model_request_fields(
+    thinking: Option<&ThinkingConfig>,
+) -> Result<Option<aws_smithy_types::Document>> {
+    thinking
+        .map(|thinking| {
+            serde_json::from_value(serde_json::json!({ "thinking": thinking }))
+                .map_err(Error::validation)
+        })
+        .transpose()
+}
+
+fn parse_max_tokens(max_tokens: u32) -> Result<i32> {
+    i32::try_from(max_tokens).map_err(|_| {
+        Error::validation(format!(
+            "max_tokens {} exceeds what Amazon Bedrock accepts",
+            max_tokens
+        ))
+    })
+}
+
+fn cache_point(cache_control: Option<&CacheControl>) -> Option<types::CachePointBlock> {
+    cache_control.and_then(|_| {
+        types::CachePointBlock::builder()
+            .r#type(types::CachePointType::Default)
+            .build()
+            .ok()
+    })
 }
 
 fn attach_tool_choice(
@@ -146,69 +412,301 @@ fn attach_tool_choice(
         messages::ToolChoiceKind::Any => {
             types::ToolChoice::Any(types::AnyToolChoice::builder().build())
         }
-        messages::ToolChoiceKind::Tool { name } => {
-            types::ToolChoice::Tool(types::SpecificToolChoice::builder().name(name).build()?)
-        }
+        messages::ToolChoiceKind::Tool { name } => types::ToolChoice::Tool(
+            types::SpecificToolChoice::builder()
+                .name(name)
+                .build()
+                .map_err(Error::validation)?,
+        ),
     })))
 }
 
-fn parse_messages(message: &Message) -> types::Message {
+fn parse_messages(messages: &[Message]) -> Result<Vec<types::Message>> {
+    let mut document_names = DocumentNames::default();
+
+    messages
+        .iter()
+        .map(|message| parse_message(message, &mut document_names))
+        .collect()
+}
+
+fn parse_message(message: &Message, document_names: &mut DocumentNames) -> Result<types::Message> {
+    let content = match message.content.to_owned() {
+        Content::Single(text) => vec![types::ContentBlock::Text(text)],
+        Content::Multi(parts) => {
+            let mut blocks = Vec::with_capacity(parts.len());
+            for part in filter_content_blocks(parts).iter() {
+                blocks.push(parse_content_part(part, document_names)?);
+                blocks
+                    .extend(cache_point(part.cache_control()).map(types::ContentBlock::CachePoint));
+            }
+            blocks
+        }
+    };
+
     types::Message::builder()
         .role(match message.role {
             messages::Role::User => types::ConversationRole::User,
             messages::Role::Assistant => types::ConversationRole::Assistant,
         })
-        .set_content(Some(match message.content.to_owned() {
-            Content::Single(text) => vec![types::ContentBlock::Text(text)],
-            Content::Multi(parts) => filter_content_blocks(parts)
-                .iter()
-                .map(|part| match part {
-                    messages::ContentPart::Text { text } => types::ContentBlock::Text(text.clone()),
-                    messages::ContentPart::Image { source } => types::ContentBlock::Image(
-                        types::ImageBlock::builder()
-                            .format(match source.media_type {
-                                MediaType::ImageJpeg => types::ImageFormat::Jpeg,
-                                MediaType::ImagePng => types::ImageFormat::Png,
-                                MediaType::ImageGif => types::ImageFormat::Gif,
-                                MediaType::ImageWebp => types::ImageFormat::Webp,
-                            })
-                            .source(types::ImageSource::Bytes(aws_smithy_types::Blob::new(
-                                aws_smithy_types::base64::decode(&source.data)
-                                    .map_err(|e| anyhow!("Failed to decode base64: {}", e))
-                                    .unwrap(),
-                            )))
-                            .build()
-                            .unwrap(),
-                    ),
-                    messages::ContentPart::ToolResult {
-                        tool_use_id,
-                        content,
-                    } => types::ContentBlock::ToolResult(
-                        types::ToolResultBlock::builder()
-                            .tool_use_id(tool_use_id)
-                            .content(types::ToolResultContentBlock::Text(content.to_owned()))
-                            .build()
-                            .unwrap(),
-                    ),
-                    messages::ContentPart::ToolUse { id, name, input } => {
-                        types::ContentBlock::ToolUse(
-                            types::ToolUseBlock::builder()
-                                .tool_use_id(id)
-                                .name(name)
-                                .input(serde_json::from_value(input.to_owned()).unwrap())
-                                .build()
-                                .unwrap(),
-                        )
-                    }
-                    messages::ContentPart::InputJsonDelta { .. }
-                    | messages::ContentPart::TextDelta { .. } => {
-                        unreachable!()
-                    }
-                })
-                .collect(),
-        }))
+        .set_content(Some(content))
         .build()
-        .expect("failed to build Message")
+        .map_err(Error::validation)
+}
+
+fn parse_content_part(
+    part: &ContentPart,
+    document_names: &mut DocumentNames,
+) -> Result<types::ContentBlock> {
+    Ok(match part {
+        ContentPart::Text { text, .. } => types::ContentBlock::Text(text.to_owned()),
+        ContentPart::Image {
+            source: ImageSource::Base64 { media_type, data },
+            ..
+        } => types::ContentBlock::Image(parse_image(media_type, data)?),
+        ContentPart::Image { .. } => {
+            return Err(Error::Unsupported(
+                "only base64 image sources can be sent to Amazon Bedrock".into(),
+            ))
+        }
+        ContentPart::Document { source, title, .. } => {
+            types::ContentBlock::Document(parse_document(source, title.as_deref(), document_names)?)
+        }
+        ContentPart::SearchResult { title, content, .. } => {
+            types::ContentBlock::Document(parse_document(
+                &DocumentSource::Content {
+                    content: Content::Multi(content.to_owned()),
+                },
+                Some(title),
+                document_names,
+            )?)
+        }
+        ContentPart::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+            ..
+        } => types::ContentBlock::ToolResult(
+            types::ToolResultBlock::builder()
+                .tool_use_id(tool_use_id)
+                .set_content(Some(parse_tool_result_content(content, document_names)?))
+                .set_status(is_error.map(|is_error| match is_error {
+                    true => types::ToolResultStatus::Error,
+                    false => types::ToolResultStatus::Success,
+                }))
+                .build()
+                .map_err(Error::validation)?,
+        ),
+        ContentPart::ToolUse {
+            id, name, input, ..
+        } => types::ContentBlock::ToolUse(
+            types::ToolUseBlock::builder()
+                .tool_use_id(id)
+                .name(name)
+                .input(serde_json::from_value(input.to_owned()).map_err(Error::validation)?)
+                .build()
+                .map_err(Error::validation)?,
+        ),
+        ContentPart::Thinking {
+            thinking,
+            signature,
+        } => types::ContentBlock::ReasoningContent(types::ReasoningContentBlock::ReasoningText(
+            types::ReasoningTextBlock::builder()
+                .text(thinking)
+                .signature(signature)
+                .build()
+                .map_err(Error::validation)?,
+        )),
+        ContentPart::RedactedThinking { data } => types::ContentBlock::ReasoningContent(
+            types::ReasoningContentBlock::RedactedContent(aws_smithy_types::Blob::new(
+                aws_smithy_types::base64::decode(data)
+                    .map_err(|e| Error::validation(format!("Failed to decode base64: {}", e)))?,
+            )),
+        ),
+        ContentPart::Unknown(_) => {
+            return Err(Error::Unsupported(
+                "unknown content blocks can't be sent to Amazon Bedrock".into(),
+            ))
+        }
+        ContentPart::InputJsonDelta { .. }
+        | ContentPart::TextDelta { .. }
+        | ContentPart::ThinkingDelta { .. }
+        | ContentPart::SignatureDelta { .. }
+        | ContentPart::CitationsDelta { .. } => {
+            return Err(Error::validation(
+                "stream deltas can't be sent as message content",
+            ))
+        }
+    })
+}
+
+fn parse_image(media_type: &MediaType, data: &str) -> Result<types::ImageBlock> {
+    types::ImageBlock::builder()
+        .format(match media_type {
+            MediaType::ImageJpeg => types::ImageFormat::Jpeg,
+            MediaType::ImagePng => types::ImageFormat::Png,
+            MediaType::ImageGif => types::ImageFormat::Gif,
+            MediaType::ImageWebp => types::ImageFormat::Webp,
+        })
+        .source(types::ImageSource::Bytes(aws_smithy_types::Blob::new(
+            aws_smithy_types::base64::decode(data)
+                .map_err(|e| Error::validation(format!("Failed to decode base64: {}", e)))?,
+        )))
+        .build()
+        .map_err(Error::validation)
+}
+
+fn parse_tool_result_content(
+    content: &Content,
+    document_names: &mut DocumentNames,
+) -> Result<Vec<types::ToolResultContentBlock>> {
+    match content {
+        Content::Single(text) => Ok(vec![types::ToolResultContentBlock::Text(text.to_owned())]),
+        Content::Multi(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text, .. } => {
+                    Ok(types::ToolResultContentBlock::Text(text.to_owned()))
+                }
+                ContentPart::Image {
+                    source: ImageSource::Base64 { media_type, data },
+                    ..
+                } => parse_image(media_type, data).map(types::ToolResultContentBlock::Image),
+                ContentPart::Document { source, title, .. } => {
+                    parse_document(source, title.as_deref(), document_names)
+                        .map(types::ToolResultContentBlock::Document)
+                }
+                _ => Err(Error::Unsupported(
+                    "tool results on Amazon Bedrock only support text, image and document content"
+                        .into(),
+                )),
+            })
+            .collect(),
+    }
+}
+
+// Unknown Bedrock shapes are tagged `bedrock_*` so they never collide with a
+// known Anthropic variant and stay `Unknown` through a serialization round trip.
+fn unknown_content_part(kind: &str) -> ContentPart {
+    ContentPart::Unknown(serde_json::json!({ "type": format!("bedrock_{}", kind) }))
+}
+
+fn unknown_content_block(block: &types::ContentBlock) -> ContentPart {
+    ContentPart::Unknown(match block {
+        types::ContentBlock::CachePoint(cache_point) => serde_json::json!({
+            "type": "bedrock_cache_point",
+            "cache_point_type": cache_point.r#type().as_str(),
+        }),
+        types::ContentBlock::Document(document) => serde_json::json!({
+            "type": "bedrock_document",
+            "name": document.name(),
+            "format": document.format().as_str(),
+        }),
+        types::ContentBlock::GuardContent(_) => {
+            serde_json::json!({ "type": "bedrock_guard_content" })
+        }
+        types::ContentBlock::Image(image) => serde_json::json!({
+            "type": "bedrock_image",
+            "format": image.format().as_str(),
+            "data": image
+                .source()
+                .and_then(|source| source.as_bytes().ok())
+                .map(|bytes| aws_smithy_types::base64::encode(bytes.as_ref())),
+        }),
+        types::ContentBlock::Video(video) => serde_json::json!({
+            "type": "bedrock_video",
+            "format": video.format().as_str(),
+        }),
+        _ => serde_json::json!({ "type": "bedrock_unknown" }),
+    })
+}
+
+fn unknown_stream_event() -> Event {
+    // The SDK's own `Unknown` stream event carries no fields to preserve.
+    Event::Unknown(serde_json::json!({ "type": "bedrock_unknown_event" }))
+}
+
+fn parse_document(
+    source: &DocumentSource,
+    title: Option<&str>,
+    document_names: &mut DocumentNames,
+) -> Result<types::DocumentBlock> {
+    let (format, bytes) = match source {
+        DocumentSource::Base64 { media_type, data } => (
+            document_format(media_type),
+            aws_smithy_types::base64::decode(data)
+                .map_err(|e| Error::validation(format!("Failed to decode base64: {}", e)))?,
+        ),
+        DocumentSource::Text { media_type, data } => {
+            (document_format(media_type), data.as_bytes().to_vec())
+        }
+        DocumentSource::Content { content } => (
+            types::DocumentFormat::Txt,
+            match content {
+                Content::Single(text) => text.to_owned(),
+                Content::Multi(parts) => parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text { text, .. } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            }
+            .into_bytes(),
+        ),
+    };
+
+    types::DocumentBlock::builder()
+        .name(document_names.next(title))
+        .format(format)
+        .source(types::DocumentSource::Bytes(aws_smithy_types::Blob::new(
+            bytes,
+        )))
+        .build()
+        .map_err(Error::validation)
+}
+
+fn document_format(media_type: &DocumentMediaType) -> types::DocumentFormat {
+    match media_type {
+        DocumentMediaType::ApplicationPdf => types::DocumentFormat::Pdf,
+        DocumentMediaType::TextPlain => types::DocumentFormat::Txt,
+    }
+}
+
+// Bedrock requires every document in a request to have a distinct name made of
+// alphanumerics, single spaces, hyphens, parentheses and square brackets.
+#[derive(Default)]
+struct DocumentNames(HashSet<String>);
+
+impl DocumentNames {
+    fn next(&mut self, title: Option<&str>) -> String {
+        let title = title
+            .unwrap_or_default()
+            .chars()
+            .map(|c| match c {
+                c if c.is_ascii_alphanumeric() => c,
+                '-' | '(' | ')' | '[' | ']' => c,
+                _ => ' ',
+            })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut count = 1;
+        loop {
+            let name = match (title.is_empty(), count) {
+                (true, count) => format!("document-{}", count),
+                (false, 1) => title.to_owned(),
+                (false, count) => format!("{} ({})", title, count),
+            };
+            if self.0.insert(name.to_owned()) {
+                return name;
+            }
+            count += 1;
+        }
+    }
 }
 
 fn parse_system(system: Content) -> Vec<types::SystemContentBlock> {
@@ -216,15 +714,30 @@ fn parse_system(system: Content) -> Vec<types::SystemContentBlock> {
         Content::Single(system) => vec![types::SystemContentBlock::Text(system)],
         Content::Multi(parts) => parts
             .iter()
-            .filter_map(|part| match part {
-                ContentPart::Text { text } => {
-                    Some(types::SystemContentBlock::Text(text.to_owned()))
-                }
+            .flat_map(|part| match part {
+                ContentPart::Text {
+                    text,
+                    cache_control,
+                    ..
+                } => std::iter::once(types::SystemContentBlock::Text(text.to_owned()))
+                    .chain(
+                        cache_point(cache_control.as_ref())
+                            .map(types::SystemContentBlock::CachePoint),
+                    )
+                    .collect(),
                 ContentPart::TextDelta { .. }
                 | ContentPart::ToolResult { .. }
                 | ContentPart::ToolUse { .. }
                 | ContentPart::Image { .. }
-                | ContentPart::InputJsonDelta { .. } => None,
+                | ContentPart::Document { .. }
+                | ContentPart::SearchResult { .. }
+                | ContentPart::InputJsonDelta { .. }
+                | ContentPart::Thinking { .. }
+                | ContentPart::RedactedThinking { .. }
+                | ContentPart::ThinkingDelta { .. }
+                | ContentPart::SignatureDelta { .. }
+                | ContentPart::CitationsDelta { .. }
+                | ContentPart::Unknown(_) => vec![],
             })
             .collect(),
     }
@@ -232,11 +745,13 @@ fn parse_system(system: Content) -> Vec<types::SystemContentBlock> {
 
 #[async_trait]
 impl Messages for AnthropicBedrock {
-    async fn messages(&self, request: CreateMessageRequest) -> Result<CreateMessageResponse> {
+    async fn messages(&self, request: CreateMessageRequest) -> Result<MessageResponse> {
+        validate_request(&request)?;
+
         let mut test_config = types::ToolConfiguration::builder();
 
         if let Some(tools) = request.tools.to_owned() {
-            test_config = attach_tools(test_config, tools);
+            test_config = attach_tools(test_config, tools)?;
         }
 
         if let Some(tool_choice) = request.tool_choice.to_owned() {
@@ -246,85 +761,247 @@ impl Messages for AnthropicBedrock {
         let mut bd_request = self
             .client
             .converse()
-            .model_id(request.model.to_owned())
-            .set_messages(Some(request.messages.iter().map(parse_messages).collect()))
+            .model_id(self.model_id(&request.model))
+            .set_messages(Some(parse_messages(
+                &self.inline_images(&request.messages).await?,
+            )?))
             .set_system(request.system.map(parse_system))
             .inference_config(
                 types::InferenceConfiguration::builder()
-                    .set_max_tokens(Some(request.max_tokens as i32))
+                    .set_max_tokens(Some(parse_max_tokens(request.max_tokens)?))
                     .set_stop_sequences(request.stop_sequences)
                     .set_temperature(request.temperature)
                     .set_top_p(request.top_p)
                     .build(),
-            );
+            )
+            .set_additional_model_request_fields(additional_model_request_fields(
+                request.thinking.as_ref(),
+            )?);
 
         if request.tools.is_some() {
-            bd_request = bd_request.set_tool_config(Some(test_config.build().unwrap()));
+            bd_request =
+                bd_request.set_tool_config(Some(test_config.build().map_err(Error::validation)?));
         }
 
-        let response = bd_request.send().await?;
+        let response = bd_request.send().await.map_err(map_sdk_error)?;
 
-        let message = response.output().unwrap();
-        let message = message.as_message().unwrap();
+        parse_response(response, request.model)
+    }
+}
 
-        Ok(CreateMessageResponse::Message(MessageResponse {
-            id: response.request_id().unwrap().to_string(),
-            model: request.model,
-            role: "assistant".to_string(),
-            content: message
-                .content()
-                .iter()
-                .map(|c| match c {
-                    types::ContentBlock::Text(text) => ContentPart::Text {
-                        text: text.to_owned(),
-                    },
-                    types::ContentBlock::Image(image_block) => ContentPart::Image {
-                        source: ImageSource {
-                            kind: "image".into(),
-                            media_type: match image_block.format() {
-                                types::ImageFormat::Jpeg => MediaType::ImageJpeg,
-                                types::ImageFormat::Png => MediaType::ImagePng,
-                                types::ImageFormat::Gif => MediaType::ImageGif,
-                                types::ImageFormat::Webp => MediaType::ImageWebp,
-                                _ => unreachable!(),
-                            },
-                            data: String::from_utf8_lossy(
-                                image_block.source().unwrap().as_bytes().unwrap().as_ref(),
-                            )
-                            .to_string(),
-                        },
-                    },
-                    types::ContentBlock::ToolResult(tool_result) => ContentPart::ToolResult {
-                        tool_use_id: tool_result.tool_use_id().to_string(),
-                        content: match tool_result.content().first() {
-                            Some(types::ToolResultContentBlock::Text(text)) => text.to_owned(),
-                            _ => unreachable!(),
-                        },
-                    },
-                    types::ContentBlock::ToolUse(tool_use) => ContentPart::ToolUse {
-                        id: tool_use.tool_use_id().to_string(),
-                        name: tool_use.name().to_string(),
-                        input: serde_json::to_value(tool_use.input()).unwrap(),
-                    },
-                    _ => unreachable!(),
-                })
-                .collect(),
-            stop_reason: Some(match response.stop_reason {
-                types::StopReason::EndTurn => StopReason::EndTurn,
-                types::StopReason::MaxTokens => StopReason::MaxTokens,
-                types::StopReason::StopSequence => StopReason::StopSequence,
-                types::StopReason::ToolUse => StopReason::ToolUse,
-                _ => unreachable!(),
+fn parse_response(
+    response: aws_sdk_bedrockruntime::operation::converse::ConverseOutput,
+    model: String,
+) -> Result<MessageResponse> {
+    let request_id = response.request_id().map(ToString::to_string);
+    let message = match response.output() {
+        Some(types::ConverseOutput::Message(message)) => message,
+        _ => {
+            return Err(ApiError {
+                status: None,
+                kind: ApiErrorKind::Api,
+                message: "Amazon Bedrock returned a response without a message".into(),
+                request_id,
+            }
+            .into())
+        }
+    };
+
+    Ok(MessageResponse {
+        id: request_id.unwrap_or_default(),
+        model,
+        role: "assistant".to_string(),
+        content: message
+            .content()
+            .iter()
+            .map(parse_content_block)
+            .collect::<Result<_>>()?,
+        stop_reason: Some(parse_stop_reason(response.stop_reason())),
+        stop_sequence: None,
+        usage: response.usage().map(parse_usage).unwrap_or_default(),
+    })
+}
+
+fn parse_content_block(block: &types::ContentBlock) -> Result<ContentPart> {
+    Ok(match block {
+        types::ContentBlock::Text(text) => ContentPart::Text {
+            text: text.to_owned(),
+            citations: None,
+            cache_control: None,
+        },
+        types::ContentBlock::Image(image_block) => {
+            parse_image_block(image_block).unwrap_or_else(|| unknown_content_block(block))
+        }
+        types::ContentBlock::ToolResult(tool_result) => ContentPart::ToolResult {
+            tool_use_id: tool_result.tool_use_id().to_string(),
+            content: Content::Multi(
+                tool_result
+                    .content()
+                    .iter()
+                    .map(parse_tool_result_block)
+                    .collect::<Result<_>>()?,
+            ),
+            is_error: tool_result
+                .status()
+                .map(|status| *status == types::ToolResultStatus::Error),
+            cache_control: None,
+        },
+        types::ContentBlock::ToolUse(tool_use) => ContentPart::ToolUse {
+            id: tool_use.tool_use_id().to_string(),
+            name: tool_use.name().to_string(),
+            input: serde_json::to_value(tool_use.input())?,
+            cache_control: None,
+        },
+        types::ContentBlock::ReasoningContent(reasoning) => match reasoning {
+            types::ReasoningContentBlock::ReasoningText(reasoning) => ContentPart::Thinking {
+                thinking: reasoning.text().to_string(),
+                signature: reasoning.signature().unwrap_or_default().to_string(),
+            },
+            types::ReasoningContentBlock::RedactedContent(data) => ContentPart::RedactedThinking {
+                data: aws_smithy_types::base64::encode(data.as_ref()),
+            },
+            _ => unknown_content_part("reasoning_content"),
+        },
+        block => unknown_content_block(block),
+    })
+}
+
+fn parse_image_block(image_block: &types::ImageBlock) -> Option<ContentPart> {
+    let media_type = match image_block.format() {
+        types::ImageFormat::Jpeg => MediaType::ImageJpeg,
+        types::ImageFormat::Png => MediaType::ImagePng,
+        types::ImageFormat::Gif => MediaType::ImageGif,
+        types::ImageFormat::Webp => MediaType::ImageWebp,
+        _ => return None,
+    };
+    let bytes = image_block
+        .source()
+        .and_then(|source| source.as_bytes().ok())?;
+
+    Some(ContentPart::Image {
+        source: ImageSource::Base64 {
+            media_type,
+            data: aws_smithy_types::base64::encode(bytes.as_ref()),
+        },
+        cache_control: None,
+    })
+}
+
+fn parse_document_block(document: &types::DocumentBlock) -> Option<ContentPart> {
+    let bytes = document
+        .source()
+        .and_then(|source| source.as_bytes().ok())?;
+    let source = match document.format() {
+        types::DocumentFormat::Pdf => {
+            DocumentSource::pdf(aws_smithy_types::base64::encode(bytes.as_ref()))
+        }
+        types::DocumentFormat::Txt => {
+            DocumentSource::text(String::from_utf8(bytes.as_ref().to_vec()).ok()?)
+        }
+        _ => return None,
+    };
+
+    Some(ContentPart::Document {
+        source,
+        title: Some(document.name().to_string()),
+        context: None,
+        citations: None,
+        cache_control: None,
+    })
+}
+
+// Blocks with no Anthropic equivalent are kept as `Unknown` rather than dropped, so a
+// tool result never silently loses content.
+fn parse_tool_result_block(block: &types::ToolResultContentBlock) -> Result<ContentPart> {
+    Ok(match block {
+        types::ToolResultContentBlock::Text(text) => ContentPart::from(text),
+        types::ToolResultContentBlock::Json(json) => {
+            ContentPart::from(serde_json::to_value(json)?.to_string())
+        }
+        types::ToolResultContentBlock::Image(image) => {
+            parse_image_block(image).unwrap_or_else(|| {
+                unknown_content_block(&types::ContentBlock::Image(image.to_owned()))
+            })
+        }
+        types::ToolResultContentBlock::Document(document) => parse_document_block(document)
+            .unwrap_or_else(|| {
+                unknown_content_block(&types::ContentBlock::Document(document.to_owned()))
             }),
-            stop_sequence: None,
-            usage: response
-                .usage
-                .map(|usage| Usage {
-                    input_tokens: Some(usage.input_tokens as u32),
-                    output_tokens: usage.output_tokens as u32,
-                })
-                .unwrap(),
-        }))
+        _ => unknown_content_part("tool_result_content"),
+    })
+}
+
+fn parse_stop_reason(stop_reason: &types::StopReason) -> StopReason {
+    match stop_reason {
+        types::StopReason::EndTurn => StopReason::EndTurn,
+        types::StopReason::MaxTokens => StopReason::MaxTokens,
+        types::StopReason::StopSequence => StopReason::StopSequence,
+        types::StopReason::ToolUse => StopReason::ToolUse,
+        stop_reason => StopReason::Unknown(stop_reason.as_str().to_string()),
+    }
+}
+
+fn parse_usage(usage: &types::TokenUsage) -> Usage {
+    Usage {
+        input_tokens: Some(usage.input_tokens as u32),
+        output_tokens: usage.output_tokens as u32,
+        cache_creation_input_tokens: usage.cache_write_input_tokens.map(|tokens| tokens as u32),
+        cache_read_input_tokens: usage.cache_read_input_tokens.map(|tokens| tokens as u32),
+    }
+}
+
+fn parse_delta(delta: Option<&types::ContentBlockDelta>) -> Result<ContentPart> {
+    Ok(match delta {
+        Some(types::ContentBlockDelta::Text(text)) => ContentPart::TextDelta {
+            text: text.to_owned(),
+        },
+        Some(types::ContentBlockDelta::ToolUse(tool_use)) => ContentPart::InputJsonDelta {
+            partial_json: tool_use.input.to_owned(),
+        },
+        Some(types::ContentBlockDelta::ReasoningContent(reasoning)) => match reasoning {
+            types::ReasoningContentBlockDelta::Text(thinking) => ContentPart::ThinkingDelta {
+                thinking: thinking.to_owned(),
+            },
+            types::ReasoningContentBlockDelta::Signature(signature) => {
+                ContentPart::SignatureDelta {
+                    signature: signature.to_owned(),
+                }
+            }
+            types::ReasoningContentBlockDelta::RedactedContent(data) => {
+                ContentPart::RedactedThinking {
+                    data: aws_smithy_types::base64::encode(data.as_ref()),
+                }
+            }
+            _ => unknown_content_part("reasoning_content_delta"),
+        },
+        Some(_) => unknown_content_part("content_block_delta"),
+        None => return Err(Error::Stream("content block delta without a delta".into())),
+    })
+}
+
+fn parse_block_start(start: Option<types::ContentBlockStart>) -> ContentPart {
+    match start {
+        Some(types::ContentBlockStart::ToolUse(tool_use)) => ContentPart::ToolUse {
+            id: tool_use.tool_use_id,
+            name: tool_use.name,
+            input: serde_json::json!({}),
+            cache_control: None,
+        },
+        Some(_) => unknown_content_part("content_block_start"),
+        None => ContentPart::Text {
+            text: "".into(),
+            citations: None,
+            cache_control: None,
+        },
+    }
+}
+
+#[async_trait]
+impl CountTokens for AnthropicBedrock {
+    async fn count_tokens(&self, _: CreateMessageRequest) -> Result<CountTokensResponse> {
+        Err(Error::Unsupported(
+            "token counting is not available on Amazon Bedrock".into(),
+        ))
     }
 }
 
@@ -334,10 +1011,12 @@ impl MessagesStream for AnthropicBedrock {
         &self,
         request: CreateMessageRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Event>> + Send>>> {
+        validate_request(&request)?;
+
         let mut test_config = types::ToolConfiguration::builder();
 
         if let Some(tools) = request.tools.to_owned() {
-            test_config = attach_tools(test_config, tools);
+            test_config = attach_tools(test_config, tools)?;
         }
 
         if let Some(tool_choice) = request.tool_choice.to_owned() {
@@ -347,141 +1026,179 @@ impl MessagesStream for AnthropicBedrock {
         let mut bd_request = self
             .client
             .converse_stream()
-            .model_id(request.model.to_owned())
-            .set_messages(Some(request.messages.iter().map(parse_messages).collect()))
+            .model_id(self.model_id(&request.model))
+            .set_messages(Some(parse_messages(
+                &self.inline_images(&request.messages).await?,
+            )?))
             .set_system(request.system.map(parse_system))
             .inference_config(
                 types::InferenceConfiguration::builder()
-                    .set_max_tokens(Some(request.max_tokens as i32))
+                    .set_max_tokens(Some(parse_max_tokens(request.max_tokens)?))
                     .set_stop_sequences(request.stop_sequences)
                     .set_temperature(request.temperature)
                     .set_top_p(request.top_p)
                     .build(),
-            );
+            )
+            .set_additional_model_request_fields(additional_model_request_fields(
+                request.thinking.as_ref(),
+            )?);
 
         if request.tools.is_some() {
-            bd_request = bd_request.set_tool_config(Some(test_config.build().unwrap()));
-        }
-
-        let response = bd_request.send().await?;
-
-        let model = request.model.clone();
-        Ok(stream! {
-            let request_id = response.request_id().unwrap().to_string();
-            let mut s = response.stream;
-            let mut event_message_delta: Option<EventMessageDelta> = None;
-            let mut block_starts = HashSet::new();
-
-            yield Ok(Event::MessageStart {
-                message: MessageResponseStream {
-                    kind: "message".into(),
-                    message_response: MessageResponse {
-                        id: request_id,
-                        model,
-                        role: "assistant".into(),
-                        content: vec![],
-                        stop_reason: None,
-                        stop_sequence: None,
-                        // FIXME: input_tokens should come from somewhere... Not sure where though
-                        usage: Usage { input_tokens: Some(0), output_tokens: 0 },
+            bd_request =
+                bd_request.set_tool_config(Some(test_config.build().map_err(Error::validation)?));
+        }
+
+        let response = bd_request.send().await.map_err(map_sdk_error)?;
+        let request_id = response.request_id().unwrap_or_default().to_string();
+        let mut receiver = response.stream;
+        let events = stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(Some(event)) => yield Ok(event),
+                    Ok(None) => break,
+                    Err(err) => {
+                        yield Err(map_sdk_error(err));
+                        break;
                     }
-                },
-            });
+                }
+            }
+        };
 
+        Ok(converse_events(events, request_id, request.model).boxed())
+    }
+}
 
-            while let Ok(Some(event)) = s.recv().await {
-                match event {
-                    types::ConverseStreamOutput::ContentBlockDelta(block_delta) => {
-                        let index = block_delta.content_block_index() as u64;
-                        if !block_starts.contains(&index) {
-                            block_starts.insert(index);
-                            yield Ok(Event::ContentBlockStart {
-                                index,
-                                content_block: ContentPart::Text { text: "".into() },
-                            });
-                        }
+fn converse_events<S>(
+    events: S,
+    request_id: String,
+    model: String,
+) -> impl Stream<Item = Result<Event>> + Send
+where
+    S: Stream<Item = Result<types::ConverseStreamOutput>> + Send,
+{
+    stream! {
+        futures::pin_mut!(events);
+        let mut event_message_delta: Option<EventMessageDelta> = None;
+        let mut block_starts = HashSet::new();
 
-                        yield Ok(Event::ContentBlockDelta {
-                            index,
-                            delta: match block_delta.delta() {
-                                Some(content_block_delta) => match content_block_delta {
-                                    types::ContentBlockDelta::Text(text) =>
-                                        ContentPart::TextDelta { text: text.to_owned() },
-                                    types::ContentBlockDelta::ToolUse(tool_use) =>
-                                        ContentPart::InputJsonDelta {
-                                            partial_json: tool_use.input.to_owned()
-                                        },
-                                    _ => unreachable!(),
-                                },
-                                None => unreachable!(),
-                            }
-                        })},
-                    types::ConverseStreamOutput::ContentBlockStart(block_start) => {
-                        let index = block_start.content_block_index as u64;
-                        if !block_starts.contains(&index) {
-                            block_starts.insert(index);
+        yield Ok(Event::MessageStart {
+            message: MessageResponseStream {
+                kind: "message".into(),
+                message_response: MessageResponse {
+                    id: request_id,
+                    model,
+                    role: "assistant".into(),
+                    content: vec![],
+                    stop_reason: None,
+                    stop_sequence: None,
+                    // Bedrock only reports usage in the trailing metadata event, which
+                    // becomes the `message_delta` usage.
+                    usage: Usage::default(),
+                }
+            },
+        });
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+
+            if event_message_delta.is_some()
+                && matches!(
+                    event,
+                    types::ConverseStreamOutput::ContentBlockDelta(_)
+                        | types::ConverseStreamOutput::ContentBlockStart(_)
+                        | types::ConverseStreamOutput::ContentBlockStop(_)
+                )
+            {
+                yield Err(Error::Stream("content block event after message stop".into()));
+                return;
+            }
+
+            match event {
+                types::ConverseStreamOutput::ContentBlockDelta(block_delta) => {
+                    let index = block_delta.content_block_index() as u64;
+                    let delta = match parse_delta(block_delta.delta()) {
+                        Ok(delta) => delta,
+                        Err(err) => {
+                            yield Err(err);
+                            return;
                         }
+                    };
 
+                    // Redacted thinking arrives whole, so it opens its block instead of
+                    // being emitted as a delta.
+                    if let ContentPart::RedactedThinking { .. } = delta {
+                        block_starts.insert(index);
+                        yield Ok(Event::ContentBlockStart { index, content_block: delta });
+                        continue;
+                    }
+
+                    if !block_starts.contains(&index) {
+                        block_starts.insert(index);
                         yield Ok(Event::ContentBlockStart {
-                        index: block_start.content_block_index as u64,
-                        content_block: match block_start.start {
-                            Some(start) => match start {
-                                types::ContentBlockStart::ToolUse(tool_use) => ContentPart::ToolUse {
-                                    id: tool_use.tool_use_id,
-                                    name: tool_use.name,
-                                    input: "{}".into(),
+                            index,
+                            content_block: match delta {
+                                ContentPart::ThinkingDelta { .. }
+                                | ContentPart::SignatureDelta { .. } => ContentPart::Thinking {
+                                    thinking: "".into(),
+                                    signature: "".into(),
                                 },
-                                _ => unreachable!()
+                                _ => ContentPart::Text { text: "".into(), citations: None, cache_control: None },
                             },
-                            None => ContentPart::Text { text: "".into() },
-                        }
-                    })},
-                    types::ConverseStreamOutput::ContentBlockStop(block_stop) =>
-                        yield Ok(Event::ContentBlockStop {
-                            index: block_stop.content_block_index as u64,
-                        }),
-                    types::ConverseStreamOutput::MessageStart(_) => {
-                        continue
-                    },
-                    types::ConverseStreamOutput::MessageStop(mess_stop) => {
-                        if event_message_delta.is_some() {
-                            yield Err(anyhow!("duplicated message delta"))
-                        } else {
-                            event_message_delta.replace(EventMessageDelta {
-                                stop_reason: match mess_stop.stop_reason {
-                                    types::StopReason::EndTurn => StopReason::EndTurn,
-                                    types::StopReason::MaxTokens => StopReason::MaxTokens,
-                                    types::StopReason::StopSequence => StopReason::StopSequence,
-                                    types::StopReason::ToolUse => StopReason::ToolUse,
-                                    _ => unreachable!(),
-                                },
-                                stop_sequence: None,
-                            });
-                            continue;
-                        }
-                    },
-                    types::ConverseStreamOutput::Metadata(metadata) =>
-                    if event_message_delta.is_none() {
-                        yield Err(anyhow!("no message delta"))
-                    } else {
-                        let metadata = metadata.usage.unwrap();
+                        });
+                    }
+
+                    yield Ok(Event::ContentBlockDelta { index, delta })
+                },
+                types::ConverseStreamOutput::ContentBlockStart(block_start) => {
+                    let index = block_start.content_block_index as u64;
+                    block_starts.insert(index);
+
+                    yield Ok(Event::ContentBlockStart {
+                        index,
+                        content_block: parse_block_start(block_start.start),
+                    })
+                },
+                types::ConverseStreamOutput::ContentBlockStop(block_stop) =>
+                    yield Ok(Event::ContentBlockStop {
+                        index: block_stop.content_block_index as u64,
+                    }),
+                types::ConverseStreamOutput::MessageStart(_) => continue,
+                types::ConverseStreamOutput::MessageStop(mess_stop) => {
+                    if event_message_delta.is_some() {
+                        yield Err(Error::Stream("duplicated message stop".into()));
+                        return;
+                    }
+
+                    event_message_delta = Some(EventMessageDelta {
+                        stop_reason: parse_stop_reason(&mess_stop.stop_reason),
+                        stop_sequence: None,
+                    });
+                },
+                types::ConverseStreamOutput::Metadata(metadata) => match event_message_delta.take() {
+                    Some(delta) => {
                         yield Ok(Event::MessageDelta {
-                            delta: event_message_delta.take().unwrap(),
-                            usage: Usage {
-                                input_tokens: Some(metadata.input_tokens as u32),
-                                output_tokens: metadata.output_tokens as u32
-                            }
+                            delta,
+                            usage: metadata.usage.as_ref().map(parse_usage).unwrap_or_default(),
                         });
-                        yield Ok(Event::MessageStop)
+                        yield Ok(Event::MessageStop);
+                        return;
                     }
-                    e => {
-                        dbg!(e);
-                        unreachable!()
-                    },
-                }
+                    None => {
+                        yield Err(Error::Stream("metadata before message stop".into()));
+                        return;
+                    }
+                },
+                _ => yield Ok(unknown_stream_event()),
             }
         }
-        .boxed())
+
+        yield Err(Error::Stream("stream ended without message_stop".into()));
     }
 }
 
@@ -491,6 +1208,462 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_tool_input_schema_round_trip() -> Result<()> {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer", "minimum": 1 },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } }
+            },
+            "additionalProperties": false,
+            "$defs": { "tag": { "type": "string", "enum": ["a", "b"] } }
+        });
+
+        let document = tool_input_schema(&ToolInputSchema::try_from(schema.clone())?)?;
+        assert_eq!(serde_json::to_value(&document)?, schema);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_names() -> Result<()> {
+        let mut names = DocumentNames::default();
+        assert_eq!(
+            names.next(Some("Q3 report: final.pdf")),
+            "Q3 report final pdf"
+        );
+        assert_eq!(
+            names.next(Some("Q3 report final pdf")),
+            "Q3 report final pdf (2)"
+        );
+        assert_eq!(names.next(Some("  ")), "document-1");
+        assert_eq!(names.next(None), "document-2");
+
+        let document = || {
+            Message::user(Content::Multi(vec![ContentPart::Document {
+                source: DocumentSource::text("The grass is green."),
+                title: Some("Facts".into()),
+                context: None,
+                citations: None,
+                cache_control: None,
+            }]))
+        };
+        let names = parse_messages(&[document(), document()])?
+            .iter()
+            .flat_map(|message| message.content())
+            .filter_map(|block| block.as_document().ok())
+            .map(|document| document.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Facts", "Facts (2)"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_mapping() {
+        let model = Model::from_anthropic(&anthropic::Model::CLAUDE_3_5_SONNET_LATEST).unwrap();
+        assert_eq!(model, Model::CLAUDE_3_5_SONNET_20241022);
+        assert_eq!(
+            model.to_anthropic(),
+            Some(anthropic::Model::CLAUDE_3_5_SONNET_20241022)
+        );
+
+        let model = Model::from("us.anthropic.claude-3-7-sonnet-20250219-v1:0");
+        assert_eq!(model.inference_profile(), Some("us"));
+        assert_eq!(
+            model.without_inference_profile(),
+            Model::CLAUDE_3_7_SONNET_20250219
+        );
+        assert_eq!(
+            model.to_anthropic(),
+            Some(anthropic::Model::CLAUDE_3_7_SONNET_20250219)
+        );
+        assert_eq!(
+            Model::CLAUDE_3_7_SONNET_20250219
+                .with_inference_profile("eu")
+                .id(),
+            "eu.anthropic.claude-3-7-sonnet-20250219-v1:0"
+        );
+
+        assert_eq!(Model::CLAUDE_3_HAIKU_20240307.inference_profile(), None);
+        assert!(Model::try_from(&anthropic::Model::from("claude-next")).is_err());
+
+        for model in [
+            Model::CLAUDE_OPUS_4_20250514,
+            Model::CLAUDE_SONNET_4_20250514,
+            Model::CLAUDE_3_7_SONNET_20250219,
+            Model::CLAUDE_3_5_SONNET_20241022,
+            Model::CLAUDE_3_5_SONNET_20240620,
+            Model::CLAUDE_3_5_HAIKU_20241022,
+            Model::CLAUDE_3_OPUS_20240229,
+            Model::CLAUDE_3_SONNET_20240229,
+            Model::CLAUDE_3_HAIKU_20240307,
+        ] {
+            let anthropic_model = model.to_anthropic().unwrap();
+            assert_eq!(Model::from_anthropic(&anthropic_model), Some(model));
+        }
+    }
+
+    #[test]
+    fn test_model_id_inference_profile() {
+        let config = aws_config::SdkConfig::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(aws_types::region::Region::new("us-east-1"))
+            .build();
+        let bedrock = AnthropicBedrock::new(&config).with_inference_profile("us");
+
+        assert_eq!(
+            bedrock.model_id("claude-3-7-sonnet-20250219"),
+            "us.anthropic.claude-3-7-sonnet-20250219-v1:0"
+        );
+        assert_eq!(
+            bedrock.model_id("anthropic.claude-3-7-sonnet-20250219-v1:0"),
+            "us.anthropic.claude-3-7-sonnet-20250219-v1:0"
+        );
+        assert_eq!(
+            bedrock.model_id("eu.anthropic.claude-3-7-sonnet-20250219-v1:0"),
+            "eu.anthropic.claude-3-7-sonnet-20250219-v1:0"
+        );
+        assert_eq!(
+            bedrock.model_id("anthropic.claude-next-20990101-v1:0"),
+            "us.anthropic.claude-next-20990101-v1:0"
+        );
+        assert_eq!(
+            bedrock.model_id("arn:aws:bedrock:us-east-1::custom-model"),
+            "arn:aws:bedrock:us-east-1::custom-model"
+        );
+    }
+
+    #[test]
+    fn test_parse_max_tokens() {
+        assert_eq!(parse_max_tokens(4096).ok(), Some(4096));
+        assert!(matches!(
+            parse_max_tokens(u32::MAX),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_sdk_error() {
+        type SdkConverseError =
+            SdkError<aws_sdk_bedrockruntime::operation::converse::ConverseError, ()>;
+
+        let err = map_sdk_error(SdkConverseError::construction_failure("missing model id"));
+        assert!(matches!(err, Error::Validation(_)));
+        assert!(!err.is_retryable());
+
+        let err = map_sdk_error(SdkConverseError::timeout_error("timed out"));
+        assert!(matches!(err, Error::Transport(_)));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_parse_messages_errors() {
+        let message = |part: ContentPart| vec![Message::user(Content::Multi(vec![part]))];
+
+        assert!(matches!(
+            parse_messages(&message(ContentPart::Unknown(
+                serde_json::json!({ "type": "video" })
+            ))),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            parse_messages(&message(ContentPart::Image {
+                source: ImageSource::url("https://example.com/cat.png"),
+                cache_control: None,
+            })),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(parse_messages(&message(ContentPart::Image {
+            source: ImageSource::base64(MediaType::ImagePng, "not base64!"),
+            cache_control: None,
+        }))
+        .is_err());
+        assert!(matches!(
+            parse_messages(&message(ContentPart::TextDelta {
+                text: "Hello".into()
+            })),
+            Err(Error::Validation(_))
+        ));
+        assert!(parse_messages(&message(ContentPart::RedactedThinking {
+            data: "%%%".into()
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_response() -> Result<()> {
+        let response = aws_sdk_bedrockruntime::operation::converse::ConverseOutput::builder()
+            .stop_reason(types::StopReason::EndTurn)
+            .build()
+            .map_err(Error::validation)?;
+        assert!(matches!(
+            parse_response(response, "model".into()),
+            Err(Error::Api(ApiError { status: None, .. }))
+        ));
+
+        let message = types::Message::builder()
+            .role(types::ConversationRole::Assistant)
+            .content(types::ContentBlock::Text("Hello".into()))
+            .content(types::ContentBlock::CachePoint(
+                types::CachePointBlock::builder()
+                    .r#type(types::CachePointType::Default)
+                    .build()
+                    .map_err(Error::validation)?,
+            ))
+            .build()
+            .map_err(Error::validation)?;
+        let response = aws_sdk_bedrockruntime::operation::converse::ConverseOutput::builder()
+            .output(types::ConverseOutput::Message(message))
+            .stop_reason(types::StopReason::from("model_context_window_exceeded"))
+            .build()
+            .map_err(Error::validation)?;
+
+        let response = parse_response(response, "model".into())?;
+        assert!(matches!(&response.content[0], ContentPart::Text { text, .. } if text == "Hello"));
+        match &response.content[1] {
+            ContentPart::Unknown(value) => assert_eq!(
+                value,
+                &serde_json::json!({ "type": "bedrock_cache_point", "cache_point_type": "default" })
+            ),
+            part => panic!("unexpected content part: {:?}", part),
+        }
+        assert!(matches!(
+            response.stop_reason,
+            Some(StopReason::Unknown(ref reason)) if reason == "model_context_window_exceeded"
+        ));
+        assert_eq!(response.usage.output_tokens, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tool_result_blocks() -> Result<()> {
+        let tool_result = types::ContentBlock::ToolResult(
+            types::ToolResultBlock::builder()
+                .tool_use_id("toolu_01")
+                .content(types::ToolResultContentBlock::Text(
+                    "Here is the chart".into(),
+                ))
+                .content(types::ToolResultContentBlock::Image(
+                    types::ImageBlock::builder()
+                        .format(types::ImageFormat::Png)
+                        .source(types::ImageSource::Bytes(aws_smithy_types::Blob::new(
+                            b"hi".to_vec(),
+                        )))
+                        .build()
+                        .map_err(Error::validation)?,
+                ))
+                .content(types::ToolResultContentBlock::Document(
+                    types::DocumentBlock::builder()
+                        .name("Notes")
+                        .format(types::DocumentFormat::Txt)
+                        .source(types::DocumentSource::Bytes(aws_smithy_types::Blob::new(
+                            b"The grass is green.".to_vec(),
+                        )))
+                        .build()
+                        .map_err(Error::validation)?,
+                ))
+                .content(types::ToolResultContentBlock::Document(
+                    types::DocumentBlock::builder()
+                        .name("Sheet")
+                        .format(types::DocumentFormat::Xlsx)
+                        .source(types::DocumentSource::Bytes(aws_smithy_types::Blob::new(
+                            vec![1, 2, 3],
+                        )))
+                        .build()
+                        .map_err(Error::validation)?,
+                ))
+                .build()
+                .map_err(Error::validation)?,
+        );
+
+        let parts = match parse_content_block(&tool_result)? {
+            ContentPart::ToolResult {
+                content: Content::Multi(parts),
+                ..
+            } => parts,
+            part => panic!("unexpected content part: {:?}", part),
+        };
+        assert_eq!(parts.len(), 4);
+        assert!(matches!(&parts[0], ContentPart::Text { text, .. } if text == "Here is the chart"));
+        assert!(matches!(
+            &parts[1],
+            ContentPart::Image {
+                source: ImageSource::Base64 { media_type: MediaType::ImagePng, data },
+                ..
+            } if data == "aGk="
+        ));
+        assert!(matches!(
+            &parts[2],
+            ContentPart::Document {
+                source: DocumentSource::Text { data, .. },
+                title: Some(title),
+                ..
+            } if data == "The grass is green." && title == "Notes"
+        ));
+        assert!(
+            matches!(&parts[3], ContentPart::Unknown(value) if value["type"] == "bedrock_document")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_round_trip() -> Result<()> {
+        let image = types::ContentBlock::Image(
+            types::ImageBlock::builder()
+                .format(types::ImageFormat::from("tiff"))
+                .source(types::ImageSource::Bytes(aws_smithy_types::Blob::new(
+                    vec![1, 2, 3],
+                )))
+                .build()
+                .map_err(Error::validation)?,
+        );
+        let part = parse_content_block(&image)?;
+        assert!(matches!(&part, ContentPart::Unknown(value) if value["type"] == "bedrock_image"));
+        let value = serde_json::to_value(&part)?;
+        assert!(matches!(
+            serde_json::from_value::<ContentPart>(value.clone())?,
+            ContentPart::Unknown(round_trip) if round_trip == value
+        ));
+
+        let value = serde_json::to_value(unknown_stream_event())?;
+        assert_eq!(
+            value,
+            serde_json::json!({ "type": "bedrock_unknown_event" })
+        );
+        assert!(matches!(
+            serde_json::from_value::<Event>(value)?,
+            Event::Unknown(_)
+        ));
+
+        Ok(())
+    }
+
+    async fn converse(events: Vec<types::ConverseStreamOutput>) -> Vec<Result<Event>> {
+        converse_events(
+            futures::stream::iter(events.into_iter().map(Ok)),
+            "req_01".into(),
+            "model".into(),
+        )
+        .collect()
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_read_image_limit() -> Result<()> {
+        let url = "https://example.com/image.png";
+        let image = vec![0u8; MAX_IMAGE_BYTES as usize];
+
+        assert_eq!(read_image(&image[..], None, url).await?.len(), image.len());
+
+        let oversized = vec![0u8; MAX_IMAGE_BYTES as usize + 1];
+        assert!(matches!(
+            read_image(&oversized[..], None, url).await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            read_image(&b""[..], Some(MAX_IMAGE_BYTES + 1), url).await,
+            Err(Error::Validation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_converse_events() -> Result<()> {
+        let delta = types::ConverseStreamOutput::ContentBlockDelta(
+            types::ContentBlockDeltaEvent::builder()
+                .content_block_index(0)
+                .delta(types::ContentBlockDelta::Text("Hi".into()))
+                .build()
+                .map_err(Error::validation)?,
+        );
+        let block_stop = types::ConverseStreamOutput::ContentBlockStop(
+            types::ContentBlockStopEvent::builder()
+                .content_block_index(0)
+                .build()
+                .map_err(Error::validation)?,
+        );
+        let message_stop = types::ConverseStreamOutput::MessageStop(
+            types::MessageStopEvent::builder()
+                .stop_reason(types::StopReason::EndTurn)
+                .build()
+                .map_err(Error::validation)?,
+        );
+        let metadata = types::ConverseStreamOutput::Metadata(
+            types::ConverseStreamMetadataEvent::builder()
+                .usage(
+                    types::TokenUsage::builder()
+                        .input_tokens(3)
+                        .output_tokens(2)
+                        .total_tokens(5)
+                        .build()
+                        .map_err(Error::validation)?,
+                )
+                .build(),
+        );
+
+        let events = converse(vec![
+            delta.clone(),
+            block_stop.clone(),
+            message_stop.clone(),
+            metadata.clone(),
+        ])
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+        assert_eq!(events.len(), 6);
+        assert!(matches!(
+            &events[0],
+            Event::MessageStart { message } if message.message_response.usage.input_tokens.is_none()
+        ));
+        assert!(matches!(
+            &events[4],
+            Event::MessageDelta { usage, .. }
+                if usage.output_tokens == 2 && usage.input_tokens == Some(3)
+        ));
+        assert!(matches!(events[5], Event::MessageStop));
+
+        let scripts = [
+            vec![
+                delta.clone(),
+                message_stop.clone(),
+                message_stop.clone(),
+                metadata.clone(),
+            ],
+            vec![metadata.clone(), delta.clone(), message_stop.clone()],
+            vec![message_stop.clone(), delta.clone(), metadata.clone()],
+            vec![delta.clone(), block_stop.clone()],
+        ];
+        for script in scripts {
+            let events = converse(script).await;
+            assert!(
+                matches!(events.last(), Some(Err(Error::Stream(_)))),
+                "{:?}",
+                events
+            );
+            assert_eq!(events.iter().filter(|event| event.is_err()).count(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_stream_blocks() {
+        assert!(matches!(parse_delta(None), Err(Error::Stream(_))));
+        assert!(matches!(
+            parse_delta(Some(&types::ContentBlockDelta::Text("Hi".into()))),
+            Ok(ContentPart::TextDelta { text }) if text == "Hi"
+        ));
+        assert!(matches!(
+            parse_block_start(None),
+            ContentPart::Text { text, .. } if text.is_empty()
+        ));
+    }
+
     #[tokio::test]
     async fn test_messages() -> Result<()> {
         let config = aws_config::defaults(BehaviorVersion::latest())
@@ -499,17 +1672,17 @@ mod tests {
             .await;
         let bedrock = AnthropicBedrock::new(&config);
         let request = CreateMessageRequest {
-            model: Model::ClaudeThreeDotFiveSonnet.to_string(),
+            model: Model::CLAUDE_3_5_SONNET_20241022.to_string(),
             messages: vec![Message {
                 role: messages::Role::User,
                 content: Content::Multi(vec![
                     "Hello".into(),
                     ContentPart::Image {
-                        source: ImageSource {
-                            kind: "base64".into(),
+                        source: ImageSource::Base64 {
                             media_type: MediaType::ImageJpeg,
                             data: "/9j/4QDKRXhpZgAATU0AKgAAAAgABgESAAMAAAABAAEAAAEaAAUAAAABAAAAVgEbAAUAAAABAAAAXgEoAAMAAAABAAIAAAITAAMAAAABAAEAAIdpAAQAAAABAAAAZgAAAAAAAABIAAAAAQAAAEgAAAABAAeQAAAHAAAABDAyMjGRAQAHAAAABAECAwCgAAAHAAAABDAxMDCgAQADAAAAAQABAACgAgAEAAAAAQAAARegAwAEAAAAAQAAANGkBgADAAAAAQAAAAAAAAAAAAD/4gHYSUNDX1BST0ZJTEUAAQEAAAHIAAAAAAQwAABtbnRyUkdCIFhZWiAH4AABAAEAAAAAAABhY3NwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAA9tYAAQAAAADTLQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAlkZXNjAAAA8AAAACRyWFlaAAABFAAAABRnWFlaAAABKAAAABRiWFlaAAABPAAAABR3dHB0AAABUAAAABRyVFJDAAABZAAAAChnVFJDAAABZAAAAChiVFJDAAABZAAAAChjcHJ0AAABjAAAADxtbHVjAAAAAAAAAAEAAAAMZW5VUwAAAAgAAAAcAHMAUgBHAEJYWVogAAAAAAAAb6IAADj1AAADkFhZWiAAAAAAAABimQAAt4UAABjaWFlaIAAAAAAAACSgAAAPhAAAts9YWVogAAAAAAAA9tYAAQAAAADTLXBhcmEAAAAAAAQAAAACZmYAAPKnAAANWQAAE9AAAApbAAAAAAAAAABtbHVjAAAAAAAAAAEAAAAMZW5VUwAAACAAAAAcAEcAbwBvAGcAbABlACAASQBuAGMALgAgADIAMAAxADb/2wCEABwcHBwcHDAcHDBEMDAwRFxEREREXHRcXFxcXHSMdHR0dHR0jIyMjIyMjIyoqKioqKjExMTExNzc3Nzc3Nzc3NwBIiQkODQ4YDQ0YOacgJzm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5ubm5v/dAAQAEv/AABEIANEBFwMBIgACEQEDEQH/xAGiAAABBQEBAQEBAQAAAAAAAAAAAQIDBAUGBwgJCgsQAAIBAwMCBAMFBQQEAAABfQECAwAEEQUSITFBBhNRYQcicRQygZGhCCNCscEVUtHwJDNicoIJChYXGBkaJSYnKCkqNDU2Nzg5OkNERUZHSElKU1RVVldYWVpjZGVmZ2hpanN0dXZ3eHl6g4SFhoeIiYqSk5SVlpeYmZqio6Slpqeoqaqys7S1tre4ubrCw8TFxsfIycrS09TV1tfY2drh4uPk5ebn6Onq8fLz9PX29/j5+gEAAwEBAQEBAQEBAQAAAAAAAAECAwQFBgcICQoLEQACAQIEBAMEBwUEBAABAncAAQIDEQQFITEGEkFRB2FxEyIygQgUQpGhscEJIzNS8BVictEKFiQ04SXxFxgZGiYnKCkqNTY3ODk6Q0RFRkdISUpTVFVWV1hZWmNkZWZnaGlqc3R1dnd4eXqCg4SFhoeIiYqSk5SVlpeYmZqio6Slpqeoqaqys7S1tre4ubrCw8TFxsfIycrS09TV1tfY2dri4+Tl5ufo6ery8/T19vf4+fr/2gAMAwEAAhEDEQA/AKT2veM1CXni4NatNIFQXYyxcS+tJvPerpgiPamfZo+3FArEIapFbHSj7PjoaUQt2oCxehuccNV0MrjisYI4qxHvX2p3FYttHT0lkUYpEm4w1SAxt3oFYTczmrSrgc1F5kMY61Tmui/ypwKAsTy3KD5Vqi8pNQUmTU3HYViSMVmTR7TWlmoZfnFNMLGcjEVcUgjFVWTbQj4pgi1tp6+1JHhutXVeJOgzSGVJMgciqz5xxWwXilGCMU1raNuBQBj5ZRVi3cnJ9KWaIxHHapI08uAt60ANRyrhjWkMPzWKHGPmNSx3Rj47UWA1HAI2mqv2ZC2RUqXETjrUysmKBkSptOBTHRijKBUkrog3E4qrHd7nx0U8UCMs8cUzNTTpseq9UhBV2ztzM+T90VDBC0zhVroliW3iEa9TQIVABz2FSZFQyEImKg8ypKP/0DpSdaaKfWZoMoCk06nqOKAGhQKD7UE9hTaYC0ZoxxSKKAJFFS7BjpSKMVNQBGIFYdKie3ZelXFOOKkGDSHYxyp9KbitXaA1QXEAA3p0oFYoUzFSYoxSJKroDVJ4yDkVpstVW+U4NUhFTcQaspMehqNkB5FRcqaoDSHYirAm2daoI236VIPnb2qRllnWU4pJnRh5KdqjcD7qVV/1bhqEAwqBwaizg81ZnxwwqD3piFyMZp+5gODUPtSdKAFYt/FU0XK1WJqeBsHFMCzJGGj+lVoLZ5m2qOK1IrYzNjoBWvFCkIwopAQ29qlunHWmZ3vnsKmmfC1WJ2JSArytufAqPYatWkXmyZPStH7MlNIL2P/RaopxpOlFZmgmKdnjApuM1OkdAEYWnbOatLFUyxgUAU2jwtRbdtX3XLYqnJ1wKABTUoBpyxBEy1OAoAQCpVGKTjFOFMYOMjNIMMpU072pg4pAZeMHFJipGHzGmYpEkZFQyICKsU3GeKYjMPymnqA1X1s9xyac8KwjgUXCxUWAn6VJKRGu1OTUDyS+nFRhz3qrCFjlZRhqVmBp4ZT2qURq3QUWFcpbu3ao81rrZFu1SDTqYGHye1SiGV/uit5bWCLG7FTSmKOE7MUgOeSAbsSHFbNvFaKOMGsXDO241ditJSu4UDNSHAkyvSrhPFZ9sHjOHFWZX2rUgV3O9/YVWmbJ2ips7EzUVvGZpR6UwNW1QRQ5PerHmrUb8kRjoKb5YrVIybP/0kYUKO1PIoQc1makipVpFxSKvFSgUgJAKfimA0M4AoEMcYaqsa5lyelStKDRDQhjZDmTb2FKSNwUVG64ufbFA7mmIcW+bAp+fSqe/mp1OaYE+eKTvQBTTSGU5RhzUdWJhnDVBSJGEU+LaG5pKMUAT+ZjpSsPMXNVwKli+WlYZWkVFHNMEantTp1LPx0qyv3QtVcViNbdPSrEUSg9KUCp14FAiUYA4qGaURpmlaRV61l3chl4XpTEUpLlnbmohI7fSpPs5wPetSGyULzQMzoFy+K3oAVGDSRWqR8irHSkIRsVQc739hViZ9q1U+6maYEM7fwitGyQRxbzWbChmlraYDiMVUUTJjox/EalytQSuI0xVXz60M7H/9OUikHBqUjmoyKzNSdXxxUu+q0dSMaAHmUDpVZ3JpppvNAEZYitC2IK1Rdcjin2sm07DTAuyD96PpUbjCmm3DDGR2qHzmZMtSBkBYBsVaiPFY3ngyVpQtkUxGgOlLimrUlA0QOuRiqtXjVOQYapExlGKKdigQYpKfikoAbThSU/FMCRabNMIxUijAqGSAOaBGaTJMav20GF+ap44USpunSgLjPJTr6VKOKSigQ8HignFA4qCV8LTAryHe+OwqtO/wDCKm+6uarRIZpQKYGlYxBE8w1cj5+c00jAEYpZGEcdaJGTKc7b3x6VDgUtFID/1L5WoiKrwXyt8kvB9e1XCARkVmaIhAp/GKUCkPtQMZto21KFwKYaYEJGKqzKw+dOCKtk1E3SgRWWZpvlq2B2qou2N8nvV1RSEY0sY3fSrls2PlpkkLfa8Do3P9KnnhEG2Rfof6VdtCb62NGM1PVGJuKug8VJYw1BIucVN1NNIxxSH0KwXFOFSUmKRAw0UuKSgAxT1FJUijApgOoFFKKBC0CkpRTAdQKYacKAHHpVKQ7mx2FTyNtWqmdq5oAhmb+EVdsIwqmQ1nIplkwK29oVViFXFEyZJGMneaq3D7mxVtyI46zM5OaozQ5adikHAopDP//VyaswXLw8dV9Kr4opAb8TpKMofw9KsbK52KR4m3JxW1b3scvyv8rfpU2LTLOO1Rlas4BqNvSkUZ8i4qAmr7rVKSMjpTAqvToLjZ+7fp29qawquwoEa0q8Bx/D/KrEirPD7MMf4fkaybe58v8Adycr/Kr0O7DQg8D9RVw7GU11IYCQMN1HH5VpKflqlOPLk8zGA/8AOpomqWjSL0LPahh0xRtzT8cUiiqRzikqR+DUdSQFGKKUUwE206iigQtLTaM0wHUtMp3agBaXNNFMdtooAhkbLY9Kqyt/CKlzgZqugMkgFMDQsoto8xqvRjcd5qMjCrEtTMRHHWiRkyncSZbbUAoJyc0o4FIBaKAKdtpAf//WygKKBS0gFpRQKUUAW4LuWHg/MvpWpHcRTjg4PpWFQOOlKw0zoMVEy5qtbXLH5ZORWhwfpU7FpmXJF6VSYYrWkO72FUXTPI6etAFIrU1pMYplB6dKjYEVHiqTE0dDJEsiGJuMcg1AimNzE38J/wD1VJbyb4FfunBqCYstxz0IGPoKuXcinpoaaU41DE2RU1ZmhWlHQ1DVqQfLVSkJjhS0lJQSLRSUlADqKZmgUwJRSk1HnFIDQBJmq0jZOKldsCqhPGaYEcrfwirVlHj94aoqDI+BW0ECosQ700iWTQjcd5/yKguZMnaKtsRHHWUx3HNWZgKdSDgUAZpDJo171LtoUYFOqRn/18rFOpKUUgHUopKWgBaKKVRk0AW4VwKvIdtV41wAKsCoKGuMn/ZFQOcjOOOwqzjNRsMnJpFIoOvFV8VZmbJ2imCPAyeKoZPYybJfLbo/FWbhCY/9qM/pWbnuvUVs7hIizdmGDVx2sYy0dyOBwQMVeFYkRaKUxHt0+la6HioNRzdKo9DV41VcYakDQ2m0tJQQJSUGmE0AGaUGmUmaAJCaM4qHNNZ8CmA53ycVA7dhTd1Ig3tQBesoh981pQjcS5/yKrDCII171cYiKOtYozkVbqTJ2Cqoppbcc04cUCHVLGveogM1bUYFSA6lpOlJuFTcZ//QzKWilpAFOpKWgAqaFcmoauwLgUmCLKipKRaWoKHCmSKSvy0+nCgDMji5y3amPk8mtJ1AQ471SKZqkVuUyp6itGwberW7d+RUJj4pY1aJg46rTTsEo3Q+VfmWTuPlNaMXK1BcKCcj7sg4qS1bK4PanIiD0LGKqy9RVtutV5R0qDR7EFNNONMNBmMNRk081EaAEzTSaDUZNAx2ahZu1BaoQSTQA4ntVqHiqoGTVtBVCLO3fhlPSmSyy/dfpSjjpUu5WGHFUmTYgUg9KkprW5HzRUxXwdrcUE2LkS96sUxMY4pxO0ZpCGO3ao80DnmnYFYtjP/RzaWilpAFLQKKAHKMmtKMYGKpQrk1fWpY0SUoFNqToKkYlOptO7UAIaZtUe1OppxigEKqeYeBwOlEiBaesu0YAqIkscmmU32HIN8LRd05X6VFAf3nHeno3lyK/bofpSSDyJ+Oh5FaboyWjNIAVFLyDjsKVDuXinEfKag1M6mU81GaCCM0w081Ex4oAiY1GTSk1ETSGNY9qsRJxzUCDJq6oxTQhvknORUW4q2KvIdtEsAcb0piI16VIKhU44NSigCRWK9KeVimGGGDUVLQAhWe35X5lpftKycdKlSQrweRQ9vFPynytTJsC9KfiqeZrbiQZHrS/bB6Vg4sLH//0s6loopALRRTkGTQBciXAqyKiUYFSioKHqKfSAYFJSAcKDRSUAFMNOptACUUlOpgIRkYpzfv7UOPvR8Gkpls/l3Jib7sg/WqiTJBDK33c8VfB+Ws7y/LmMR/Cptxxih6FKWgxjzURpxNRmkIa1V2NPY1XY0ANNRmnGhBk0hk8S4FWBTFFSCqEKKmjfacdqipaBEs0IYb0qsp7GrkUmOD0pJ4P40pgQCnVEp7VIKQDqUcdKSloAsrMMbZBkU7fB/dqrRTA//Tz6KSnAZpAABPSrUSYpiKBU60rjsTCpFqMVKKkB2aBTadQAUUUtIYlMpxpvagBBTqQUtMAqtcA7N69V5FWTUbDIxTQiWcie3ju4+3Wo8gjI70zTnAeSyfoeVoUFC0Lfw9PpVvYlCGo2p5qFzUlELmoKcxqOpGIasRrgVAgyauKKaEPFPFNFOpiFFLSU6gBaswyfwtValoAkuLf+NKqqa0opARtaq9xb4+dKYiIU6oVNSg0hjqWkpaYH//1M2pkqGpkpASipVqIVKtSMmWpaiWpaQBTqbTqAClFJSikA1qaac1NNMYo6UopB0pRQIbSUtJTAqQf8hOOrlz/wAfh/3apwf8hOOrlz/x+H/dq1sT1K5qu9WDVd6koqmmmnGmmpGSRVaFVYqtCqEOFPFMFPFAhadTadQAtFFFMB69RV8/6uqC9RV8/wCroEZB+8aetMP3jT1pDJRS0gpaYH//2Q==".into(),
                         },
+                        cache_control: None,
                     },
                 ]),
             }],
@@ -517,6 +1690,7 @@ mod tests {
             max_tokens: 4096,
             stop_sequences: None,
             temperature: Some(0.5),
+            thinking: None,
             top_p: None,
             metadata: None,
             tool_choice: None,
@@ -1,15 +1,22 @@
-use std::{str::FromStr, sync::Arc};
+use std::sync::Arc;
 
-use anthropic::messages::{
-    Content, CreateMessageRequestWithStream, Message, Metadata, Requester, Tool, ToolChoice,
+use anthropic::{
+    messages::{
+        Content, CountTokens, CountTokensRequest, CountTokensResponse, CreateMessageRequest,
+        CreateMessageRequestWithStream, Message, Metadata, Requester, ThinkingConfig, Tool,
+        ToolChoice,
+    },
+    response,
+    retry::RetryPolicy,
+    Error, ModelCapabilities, Provider, Result,
 };
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use google_cloud_auth::{project::Config, token::DefaultTokenSourceProvider};
 use google_cloud_token::{TokenSource, TokenSourceProvider as _};
 use http_client::{
     http::{
-        header::{AUTHORIZATION, CONTENT_TYPE},
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+        request::Builder,
         Method, Request,
     },
     AsyncBody, HttpClient, RequestBuilderExt,
@@ -19,52 +26,63 @@ pub use anthropic::messages;
 
 const DEFAULT_API_VERSION: &str = "vertex-2023-10-16";
 
-pub enum Model {
-    ClaudeThreeDotFiveSonnet,
-    ClaudeThreeDotFiveSonnetV1,
-    ClaudeThreeSonnet,
-    ClaudeThreeOpus,
-    ClaudeThreeHaiku,
-}
+anthropic::__private::model_type!(Model);
+
+impl Model {
+    pub const CLAUDE_OPUS_4_20250514: Model = Model::from_static("claude-opus-4@20250514");
+    pub const CLAUDE_SONNET_4_20250514: Model = Model::from_static("claude-sonnet-4@20250514");
+    pub const CLAUDE_3_7_SONNET_20250219: Model = Model::from_static("claude-3-7-sonnet@20250219");
+    pub const CLAUDE_3_5_SONNET_20241022: Model =
+        Model::from_static("claude-3-5-sonnet-v2@20241022");
+    pub const CLAUDE_3_5_SONNET_20240620: Model = Model::from_static("claude-3-5-sonnet@20240620");
+    pub const CLAUDE_3_5_HAIKU_20241022: Model = Model::from_static("claude-3-5-haiku@20241022");
+    pub const CLAUDE_3_OPUS_20240229: Model = Model::from_static("claude-3-opus@20240229");
+    pub const CLAUDE_3_SONNET_20240229: Model = Model::from_static("claude-3-sonnet@20240229");
+    pub const CLAUDE_3_HAIKU_20240307: Model = Model::from_static("claude-3-haiku@20240307");
+
+    pub fn capabilities(&self) -> Option<&'static ModelCapabilities> {
+        self.to_anthropic().and_then(|model| model.capabilities())
+    }
 
-impl ToString for Model {
-    fn to_string(&self) -> String {
-        match self {
-            Model::ClaudeThreeDotFiveSonnet => "claude-3-5-sonnet-v2@20241022".to_string(),
-            Model::ClaudeThreeDotFiveSonnetV1 => "claude-3-5-sonnet@20240620".to_string(),
-            Model::ClaudeThreeSonnet => "claude-3-sonnet@20240229".to_string(),
-            Model::ClaudeThreeOpus => "claude-3-opus@20240229".to_string(),
-            Model::ClaudeThreeHaiku => "claude-3-haiku@20240307".to_string(),
-        }
+    pub fn from_anthropic(model: &anthropic::Model) -> Option<Self> {
+        model
+            .provider_id(Provider::VertexAi)
+            .map(Model::from_static)
+    }
+
+    pub fn to_anthropic(&self) -> Option<anthropic::Model> {
+        anthropic::Model::from_provider_id(Provider::VertexAi, self.id())
     }
 }
 
-impl FromStr for Model {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "claude-3-5-sonnet-v2@20241022" => Ok(Model::ClaudeThreeDotFiveSonnet),
-            "claude-3-5-sonnet@20240620" => Ok(Model::ClaudeThreeDotFiveSonnetV1),
-            "claude-3-sonnet@20240229" => Ok(Model::ClaudeThreeSonnet),
-            "claude-3-opus@20240229" => Ok(Model::ClaudeThreeOpus),
-            "claude-3-haiku@20240307" => Ok(Model::ClaudeThreeHaiku),
-            _ => Err(anyhow::anyhow!("model not supported: {}", s)),
-        }
+impl TryFrom<&anthropic::Model> for Model {
+    type Error = Error;
+
+    fn try_from(model: &anthropic::Model) -> Result<Self, Self::Error> {
+        Model::from_anthropic(model)
+            .ok_or_else(|| Error::validation(format!("no Vertex AI model id known for {}", model)))
     }
 }
 
+fn model_id(model: &str) -> String {
+    Model::from_anthropic(&anthropic::Model::from(model))
+        .map(|model| model.to_string())
+        .unwrap_or_else(|| model.to_string())
+}
+
 pub struct AnthropicVertexAi {
     http_client: Arc<dyn HttpClient>,
     project: String,
     region: String,
     token_source: Arc<dyn TokenSource>,
+    retry_policy: RetryPolicy,
 }
 
 pub struct AnthropicVertexAiBuilder {
     project: Option<String>,
     region: Option<String>,
     http_client: Option<Arc<dyn HttpClient>>,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl AnthropicVertexAi {
@@ -73,8 +91,20 @@ impl AnthropicVertexAi {
             project: None,
             region: None,
             http_client: None,
+            retry_policy: None,
         }
     }
+
+    async fn request(&self, url: String) -> Result<Builder> {
+        Ok(Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header("x-goog-user-project", &self.project)
+            .header(
+                AUTHORIZATION,
+                self.token_source.token().await.map_err(Error::Auth)?,
+            ))
+    }
 }
 
 impl AnthropicVertexAiBuilder {
@@ -93,6 +123,11 @@ impl AnthropicVertexAiBuilder {
         self
     }
 
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
     pub async fn build(&self) -> Result<AnthropicVertexAi> {
         let config = Config {
             audience: None,
@@ -100,14 +135,26 @@ impl AnthropicVertexAiBuilder {
             sub: None,
         };
 
-        let tsp = DefaultTokenSourceProvider::new(config).await?;
+        let tsp = DefaultTokenSourceProvider::new(config)
+            .await
+            .map_err(Error::auth)?;
         let ts = tsp.token_source();
 
         Ok(AnthropicVertexAi {
-            project: self.project.to_owned().unwrap(),
-            region: self.region.to_owned().unwrap(),
+            project: self
+                .project
+                .to_owned()
+                .ok_or_else(|| Error::validation("project is required"))?,
+            region: self
+                .region
+                .to_owned()
+                .ok_or_else(|| Error::validation("region is required"))?,
             token_source: ts,
-            http_client: self.http_client.to_owned().unwrap(),
+            http_client: self
+                .http_client
+                .to_owned()
+                .ok_or_else(|| Error::validation("http client is required"))?,
+            retry_policy: self.retry_policy.to_owned().unwrap_or_default(),
         })
     }
 }
@@ -125,6 +172,8 @@ struct VertexAiCreateMessageRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
@@ -143,8 +192,9 @@ impl From<CreateMessageRequestWithStream> for VertexAiCreateMessageRequest {
             max_tokens: value.create_message_request.max_tokens,
             metadata: value.create_message_request.metadata,
             stop_sequences: value.create_message_request.stop_sequences,
-            system: value.create_message_request.system.map(|s| s.into()),
+            system: value.create_message_request.system,
             temperature: value.create_message_request.temperature,
+            thinking: value.create_message_request.thinking,
             tool_choice: value.create_message_request.tool_choice,
             tools: value.create_message_request.tools,
             top_k: value.create_message_request.top_k,
@@ -176,33 +226,53 @@ impl Requester for AnthropicVertexAi {
         };
         format!(
             "/models/{}:{}",
-            body.create_message_request.model.to_string(),
+            model_id(&body.create_message_request.model),
             return_type
         )
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
     async fn request_builder(
         &self,
         url: String,
         body: CreateMessageRequestWithStream,
     ) -> Result<Request<AsyncBody>> {
-        let mut req = Request::builder().method(Method::POST).uri(url);
+        let mut req = self.request(url).await?;
 
         if body.stream {
-            req = req.header("X-Stainless-Helper-Method", "stream");
+            req = req
+                .header(ACCEPT, "text/event-stream")
+                .header("X-Stainless-Helper-Method", "stream");
         }
 
-        Ok(req
-            .header("x-goog-user-project", &self.project)
-            .header(
-                AUTHORIZATION,
-                self.token_source
-                    .token()
-                    .await
-                    .map_err(|err| anyhow!("{:?}", err))?,
-            )
-            .header(CONTENT_TYPE, "application/json")
-            .json(dbg!(VertexAiCreateMessageRequest::from(body)))?)
+        req.header(CONTENT_TYPE, "application/json")
+            .json(VertexAiCreateMessageRequest::from(body))
+            .map_err(Error::validation)
+    }
+}
+
+#[async_trait]
+impl CountTokens for AnthropicVertexAi {
+    async fn count_tokens(&self, request: CreateMessageRequest) -> Result<CountTokensResponse> {
+        let url = format!("{}/models/count-tokens:rawPredict", self.base_url());
+        let mut body = CountTokensRequest::from(request);
+        body.model = model_id(&body.model);
+
+        let response = self
+            .retry_policy
+            .send(self.http_client.as_ref(), || async {
+                self.request(url.clone())
+                    .await?
+                    .header(CONTENT_TYPE, "application/json")
+                    .json(&body)
+                    .map_err(Error::validation)
+            })
+            .await?;
+
+        response::read_json(response).await
     }
 }
 
@@ -215,11 +285,47 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_model_mapping() {
+        assert_eq!(
+            Model::from_anthropic(&anthropic::Model::CLAUDE_3_5_SONNET_LATEST),
+            Some(Model::CLAUDE_3_5_SONNET_20241022)
+        );
+        assert_eq!(
+            Model::CLAUDE_3_5_SONNET_20240620.to_anthropic(),
+            Some(anthropic::Model::CLAUDE_3_5_SONNET_20240620)
+        );
+        assert_eq!(
+            model_id("claude-3-5-sonnet-20241022"),
+            "claude-3-5-sonnet-v2@20241022"
+        );
+        assert_eq!(
+            model_id("claude-3-haiku@20240307"),
+            "claude-3-haiku@20240307"
+        );
+
+        for model in [
+            Model::CLAUDE_OPUS_4_20250514,
+            Model::CLAUDE_SONNET_4_20250514,
+            Model::CLAUDE_3_7_SONNET_20250219,
+            Model::CLAUDE_3_5_SONNET_20241022,
+            Model::CLAUDE_3_5_SONNET_20240620,
+            Model::CLAUDE_3_5_HAIKU_20241022,
+            Model::CLAUDE_3_OPUS_20240229,
+            Model::CLAUDE_3_SONNET_20240229,
+            Model::CLAUDE_3_HAIKU_20240307,
+        ] {
+            let anthropic_model = model.to_anthropic().unwrap();
+            assert_eq!(Model::from_anthropic(&anthropic_model), Some(model));
+        }
+    }
+
     #[tokio::test]
+    #[allow(clippy::assertions_on_constants)]
     async fn test_messages() -> Result<()> {
         let client = AnthropicVertexAi::builder()
-            .with_project(std::env::var("GCLOUD_PROJECT_ID")?)
-            .with_region(std::env::var("GCLOUD_REGION")?)
+            .with_project(std::env::var("GCLOUD_PROJECT_ID").map_err(Error::validation)?)
+            .with_region(std::env::var("GCLOUD_REGION").map_err(Error::validation)?)
             .with_http_client(Arc::new(HttpClientReqwest::default()))
             .build()
             .await?;
@@ -228,7 +334,7 @@ mod tests {
             client
                 .messages(
                     CreateMessageRequest::builder()
-                        .model(Model::ClaudeThreeDotFiveSonnet)
+                        .model(Model::CLAUDE_3_5_SONNET_20241022)
                         .messages(vec![Message::user("Hi!".into())])
                         .max_tokens(1024)
                         .build()?,
@@ -242,10 +348,11 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::assertions_on_constants)]
     async fn test_messages_stream() -> Result<()> {
         let client = AnthropicVertexAi::builder()
-            .with_project(std::env::var("GCLOUD_PROJECT_ID")?)
-            .with_region(std::env::var("GCLOUD_REGION")?)
+            .with_project(std::env::var("GCLOUD_PROJECT_ID").map_err(Error::validation)?)
+            .with_region(std::env::var("GCLOUD_REGION").map_err(Error::validation)?)
             .with_http_client(Arc::new(HttpClientReqwest::default()))
             .build()
             .await?;
@@ -253,7 +360,7 @@ mod tests {
         let mut s = client
             .messages_stream(
                 CreateMessageRequest::builder()
-                    .model(Model::ClaudeThreeDotFiveSonnet)
+                    .model(Model::CLAUDE_3_5_SONNET_20241022)
                     .messages(vec![Message::user("Hi!".into())])
                     .max_tokens(100)
                     .build()?,